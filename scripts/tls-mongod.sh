@@ -0,0 +1,49 @@
+#!/usr/bin/env sh
+# 產生自簽的 CA、伺服器與用戶端憑證, 並啟動只接受 TLS 的本機 mongod, 供 tests/tls.rs 使用
+#
+#   scripts/tls-mongod.sh            # 啟動, 憑證與資料放在 target/tls
+#   cargo test --test tls -- --ignored
+#   scripts/tls-mongod.sh stop
+set -eu
+
+DIR=${KANA_TLS_TEST_DIR:-target/tls}
+PORT=${KANA_TLS_TEST_PORT:-27443}
+CLIENT_SUBJECT="/O=kana/OU=importer/CN=kana_importer"
+
+if [ "${1:-start}" = "stop" ]; then
+    mongod --dbpath "$DIR/db" --shutdown
+    exit 0
+fi
+
+mkdir -p "$DIR/db"
+cd "$DIR"
+
+# CA
+openssl req -x509 -newkey rsa:2048 -nodes -days 30 -subj "/O=kana/CN=kana test CA" \
+    -addext "basicConstraints=critical,CA:TRUE" -addext "keyUsage=critical,keyCertSign,cRLSign" \
+    -keyout ca.key -out ca.pem
+
+# 伺服器憑證: 主機名稱為 localhost 與 127.0.0.1
+openssl req -newkey rsa:2048 -nodes -subj "/O=kana/OU=server/CN=localhost" -keyout server.key -out server.csr
+printf 'subjectAltName=DNS:localhost,IP:127.0.0.1\nextendedKeyUsage=serverAuth\n' > server.ext
+openssl x509 -req -in server.csr -CA ca.pem -CAkey ca.key -CAcreateserial -days 30 -extfile server.ext -out server.crt
+cat server.crt server.key > server.pem
+
+# 用戶端憑證: subject 即為 MONGODB-X509 的帳號
+openssl req -newkey rsa:2048 -nodes -subj "$CLIENT_SUBJECT" -keyout client.key -out client.csr
+printf 'extendedKeyUsage=clientAuth\n' > client.ext
+openssl x509 -req -in client.csr -CA ca.pem -CAkey ca.key -CAcreateserial -days 30 -extfile client.ext -out client.crt
+cat client.crt client.key > client.pem
+
+mongod --dbpath db --port "$PORT" --bind_ip localhost --auth --fork --logpath mongod.log \
+    --tlsMode requireTLS --tlsCertificateKeyFile server.pem --tlsCAFile ca.pem
+
+# 以 localhost exception 建立第一個 (也是唯一的) 使用者
+USER=$(openssl x509 -in client.crt -noout -subject -nameopt RFC2253 | sed 's/^subject=//')
+mongosh --quiet "mongodb://localhost:$PORT/?tls=true" --tlsCAFile ca.pem --tlsCertificateKeyFile client.pem --eval "
+    db.getSiblingDB('\$external').createUser({
+        user: '$USER',
+        roles: [{ role: 'dbOwner', db: 'jp_syllabaries_tls_test' }],
+    })"
+
+echo "mongod is listening on localhost:$PORT with TLS, X.509 user: $USER"
@@ -0,0 +1,69 @@
+use super::DatasetError;
+use crate::model::{KanaMapping, KanaType};
+use std::collections::HashSet;
+
+// 標準拗音表: 清音、濁音與半濁音的い段假名加上小寫的ゃゅょ
+const YOUON_INITIALS: [char; 12] = ['き', 'し', 'ち', 'に', 'ひ', 'み', 'り', 'ぎ', 'じ', 'ぢ', 'び', 'ぴ'];
+const YOUON_GLIDES: [char; 3] = ['ゃ', 'ゅ', 'ょ'];
+
+// 平假名與片假名在 Unicode 中相差 0x60
+fn to_katakana(hiragana: &str) -> String {
+    hiragana.chars().map(|c| char::from_u32(c as u32 + 0x60).unwrap_or(c)).collect()
+}
+
+// 平假名與片假名的標準拗音, 共 2 × 12 × 3 格
+pub fn standard_youon() -> Vec<(KanaType, String)> {
+    let hiragana: Vec<String> = YOUON_INITIALS
+        .iter()
+        .flat_map(|initial| YOUON_GLIDES.iter().map(move |glide| format!("{}{}", initial, glide)))
+        .collect();
+    let katakana = hiragana.iter().map(|kana| (KanaType::Katakana, to_katakana(kana)));
+    hiragana.iter().map(|kana| (KanaType::Hiragana, kana.clone())).chain(katakana).collect()
+}
+
+// 缺少標準拗音表中的任何一格時回傳錯誤, 並列出所有缺少的假名
+pub fn check_youon(mappings: &[KanaMapping], source: &str) -> Result<(), DatasetError> {
+    let present: HashSet<(KanaType, &str)> = mappings.iter().map(KanaMapping::key).collect();
+    let missing: Vec<String> = standard_youon()
+        .iter()
+        .filter(|(kana_type, kana)| !present.contains(&(*kana_type, kana.as_str())))
+        .map(|(kana_type, kana)| format!("{} ({})", kana, kana_type))
+        .collect();
+    if missing.is_empty() {
+        Ok(())
+    } else {
+        Err(DatasetError::new(source, format!("incomplete youon table, missing {}", missing.join(", "))))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dataset;
+
+    #[test]
+    fn builtin_dataset_has_every_standard_youon() {
+        let mappings = dataset::builtin().unwrap();
+        assert!(check_youon(&mappings, "builtin").is_ok());
+    }
+
+    #[test]
+    fn lists_every_missing_youon() {
+        let mappings: Vec<KanaMapping> =
+            dataset::builtin().unwrap().into_iter().filter(|mapping| mapping.kana != "ぎゃ" && mapping.kana != "ギャ").collect();
+        let error = check_youon(&mappings, "kana.csv").unwrap_err();
+        assert_eq!(error.message, "incomplete youon table, missing ぎゃ (hiragana), ギャ (katakana)");
+    }
+
+    #[test]
+    fn katakana_is_hiragana_shifted_by_0x60() {
+        assert_eq!(to_katakana("きゃ"), "キャ");
+        assert_eq!(to_katakana("ぢょ"), "ヂョ");
+        assert_eq!(to_katakana("ぴゅ"), "ピュ");
+
+        let youon = standard_youon();
+        assert_eq!(youon.len(), 72);
+        assert_eq!(youon[0], (KanaType::Hiragana, "きゃ".to_string()));
+        assert_eq!(youon[36], (KanaType::Katakana, "キャ".to_string()));
+    }
+}
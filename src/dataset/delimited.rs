@@ -0,0 +1,262 @@
+use super::{write_error, DatasetError, DatasetReader, DatasetWriter, Record, Records};
+use crate::model::{Category, KanaMapping, KanaType, Role, OPTIONAL_FIELDS};
+use crate::parse::parse_bool;
+use mongodb::bson::Bson;
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::{Read, Write};
+
+// 每一列必須具備的欄位, 其餘欄位會放進 KanaMapping.extra
+pub const REQUIRED_COLUMNS: [&str; 4] = ["kana_type", "category", "kana", "romaji"];
+
+// 對應到 KanaMapping 具型別欄位的選用欄位, 空白表示未設定
+pub const TYPED_COLUMNS: [&str; 5] = OPTIONAL_FIELDS;
+
+fn typed_value(mapping: &KanaMapping, column: &str) -> Option<String> {
+    match column {
+        "usage" => mapping.usage.map(|usage| usage.to_string()),
+        "role" => mapping.role.map(|role| role.to_string()),
+        "fullwidth" => mapping.fullwidth.clone(),
+        "modern_kana" => mapping.modern_kana.clone(),
+        "beginner" => mapping.beginner.map(|beginner| beginner.to_string()),
+        _ => None,
+    }
+}
+
+// CSV 與 TSV 共用的解析器, `#` 開頭的行視為註解
+pub struct DelimitedReader {
+    pub delimiter: u8,
+}
+
+impl DatasetReader for DelimitedReader {
+    fn read(&self, input: Box<dyn Read>, source: &str) -> Result<Records, DatasetError> {
+        // csv 內建的註解處理不會把註解計入行號, 因此自行略過 `#` 開頭的列, 標題列為第一個非註解列
+        let mut records = csv::ReaderBuilder::new()
+            .delimiter(self.delimiter)
+            .has_headers(false)
+            .trim(csv::Trim::All)
+            .flexible(true)
+            .from_reader(input)
+            .into_records()
+            .filter(|result| !matches!(result, Ok(record) if is_comment(record)));
+
+        let headers = records.next().transpose().map_err(|e| csv_error(source, e))?.unwrap_or_default();
+        let header_line = headers.position().map_or(1, |position| position.line());
+
+        // 找出必要欄位在標題列中的位置
+        let mut indexes = [0usize; REQUIRED_COLUMNS.len()];
+        for (slot, name) in indexes.iter_mut().zip(REQUIRED_COLUMNS) {
+            *slot = headers.iter().position(|header| header == name).ok_or_else(|| {
+                DatasetError::new(source, format!("missing column `{}` in header", name)).at(Some(header_line), None)
+            })?;
+        }
+        let typed = TYPED_COLUMNS.map(|name| headers.iter().position(|header| header == name));
+
+        let source = source.to_string();
+        Ok(Box::new(records.map(move |result| {
+            let record = result.map_err(|e| csv_error(&source, e))?;
+            let line = record.position().map(|position| position.line());
+
+            // 列尾空白的選用欄位可以省略, 新增欄位時不必改寫既有的列
+            if record.len() > headers.len() {
+                return Err(DatasetError::new(
+                    &source,
+                    format!("expected at most {} columns, found {}", headers.len(), record.len()),
+                )
+                .at(line, Some(headers.len() + 1)));
+            }
+
+            let [usage, role, fullwidth, modern_kana, beginner] = typed;
+            let role: Option<Role> = parse_typed(&record, role, line, &source, str::parse)?;
+
+            let mut values = [""; REQUIRED_COLUMNS.len()];
+            for ((value, &index), name) in values.iter_mut().zip(&indexes).zip(REQUIRED_COLUMNS) {
+                *value = record.get(index).unwrap_or_default();
+                // 促音與長音符號沒有羅馬拼音
+                let optional = name == "romaji" && role.is_some_and(|role| !role.has_romaji());
+                if value.is_empty() && !optional {
+                    return Err(DatasetError::new(&source, format!("empty value for column `{}`", name))
+                        .at(line, Some(index + 1)));
+                }
+            }
+
+            let extra: BTreeMap<String, Bson> = headers
+                .iter()
+                .zip(record.iter())
+                .enumerate()
+                .filter(|(index, (_, value))| {
+                    !indexes.contains(index) && !typed.contains(&Some(*index)) && !value.is_empty()
+                })
+                .map(|(_, (header, value))| (header.to_string(), Bson::String(value.to_string())))
+                .collect();
+
+            let [kana_type, category, kana, romaji] = values;
+            let kana_type: KanaType = kana_type
+                .parse()
+                .map_err(|message: String| DatasetError::new(&source, message).at(line, Some(indexes[0] + 1)))?;
+            let category: Category = category
+                .parse()
+                .map_err(|message: String| DatasetError::new(&source, message).at(line, Some(indexes[1] + 1)))?;
+            Ok(Record {
+                line,
+                mapping: KanaMapping {
+                    kana_type,
+                    category,
+                    kana: kana.to_string(),
+                    romaji: romaji.to_string(),
+                    usage: parse_typed(&record, usage, line, &source, str::parse)?,
+                    role,
+                    fullwidth: parse_typed(&record, fullwidth, line, &source, |value| Ok(value.to_string()))?,
+                    modern_kana: parse_typed(&record, modern_kana, line, &source, |value| Ok(value.to_string()))?,
+                    beginner: parse_typed(&record, beginner, line, &source, parse_bool)?,
+                    extra,
+                },
+            })
+        })))
+    }
+}
+
+fn is_comment(record: &csv::StringRecord) -> bool {
+    record.get(0).is_some_and(|field| field.starts_with('#'))
+}
+
+fn parse_typed<T>(
+    record: &csv::StringRecord,
+    index: Option<usize>,
+    line: Option<u64>,
+    source: &str,
+    parse: impl Fn(&str) -> Result<T, String>,
+) -> Result<Option<T>, DatasetError> {
+    match index.and_then(|index| Some((index, record.get(index)?))) {
+        Some((index, value)) if !value.is_empty() => {
+            parse(value).map(Some).map_err(|message| DatasetError::new(source, message).at(line, Some(index + 1)))
+        }
+        _ => Ok(None),
+    }
+}
+
+// 有值的具型別欄位接在必要欄位之後, 額外欄位再依名稱排序接在最後
+pub struct DelimitedWriter {
+    pub delimiter: u8,
+}
+
+impl DatasetWriter for DelimitedWriter {
+    fn write(&self, mappings: &[KanaMapping], output: &mut dyn Write, destination: &str) -> Result<(), DatasetError> {
+        let typed_columns: Vec<&str> = TYPED_COLUMNS
+            .into_iter()
+            .filter(|column| mappings.iter().any(|mapping| typed_value(mapping, column).is_some()))
+            .collect();
+        let extra_columns: BTreeSet<&String> = mappings.iter().flat_map(|mapping| mapping.extra.keys()).collect();
+        let mut writer = csv::WriterBuilder::new().delimiter(self.delimiter).from_writer(output);
+
+        let headers = REQUIRED_COLUMNS
+            .iter()
+            .copied()
+            .chain(typed_columns.iter().copied())
+            .chain(extra_columns.iter().map(|column| column.as_str()));
+        writer.write_record(headers).map_err(|e| write_error(destination, e))?;
+
+        for mapping in mappings {
+            let mut row = vec![
+                mapping.kana_type.to_string(),
+                mapping.category.to_string(),
+                mapping.kana.clone(),
+                mapping.romaji.clone(),
+            ];
+            row.extend(typed_columns.iter().map(|column| typed_value(mapping, column).unwrap_or_default()));
+            row.extend(extra_columns.iter().map(|column| match mapping.extra.get(*column) {
+                Some(Bson::String(value)) => value.clone(),
+                Some(value) => value.to_string(),
+                None => String::new(),
+            }));
+            writer.write_record(&row).map_err(|e| write_error(destination, e))?;
+        }
+        writer.flush().map_err(|e| write_error(destination, e))
+    }
+}
+
+fn csv_error(source: &str, error: csv::Error) -> DatasetError {
+    let line = error.position().map(|position| position.line());
+    match error.kind() {
+        csv::ErrorKind::Utf8 { err, .. } => {
+            DatasetError::new(source, "invalid UTF-8").at(line, Some(err.field() + 1))
+        }
+        _ => DatasetError::new(source, error.to_string()).at(line, None),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::Usage;
+    use std::io::Cursor;
+
+    fn read(input: &str) -> Result<Vec<Record>, DatasetError> {
+        let reader = DelimitedReader { delimiter: b',' };
+        reader.read(Box::new(Cursor::new(input.to_string().into_bytes())), "test.csv")?.collect()
+    }
+
+    fn read_error(input: &str) -> String {
+        read(input).unwrap_err().to_string()
+    }
+
+    #[test]
+    fn reports_missing_header_column() {
+        assert_eq!(read_error("kana_type,category,kana\nhiragana,seion,あ\n"), "test.csv:1: missing column `romaji` in header");
+        assert_eq!(read_error(""), "test.csv:1: missing column `kana_type` in header");
+        assert_eq!(read_error("# 註解\nkana_type,category,romaji\n"), "test.csv:2: missing column `kana` in header");
+    }
+
+    #[test]
+    fn reports_surplus_columns_at_the_first_one() {
+        let input = "kana_type,category,kana,romaji\nhiragana,seion,あ,a,extra\n";
+        assert_eq!(read_error(input), "test.csv:2:5: expected at most 4 columns, found 5");
+    }
+
+    #[test]
+    fn rows_may_omit_trailing_optional_columns() {
+        let input = "kana_type,category,kana,romaji,usage,role,note\nhiragana,seion,あ,a\nkatakana,extended_katakana,ヴ,vu,rare\n";
+        let records = read(input).unwrap();
+        assert_eq!(records[0].mapping.usage, None);
+        assert!(records[0].mapping.extra.is_empty());
+        assert_eq!(records[1].mapping.usage, Some(Usage::Rare));
+        assert_eq!(records[1].mapping.role, None);
+
+        // 必要欄位仍不可省略
+        let input = "kana_type,category,romaji,kana\nhiragana,seion,a\n";
+        assert_eq!(read_error(input), "test.csv:2:4: empty value for column `kana`");
+    }
+
+    #[test]
+    fn reports_empty_required_value_with_column() {
+        let input = "kana_type,category,kana,romaji\nhiragana,seion,あ,a\nhiragana,seion,,i\n";
+        assert_eq!(read_error(input), "test.csv:3:3: empty value for column `kana`");
+    }
+
+    #[test]
+    fn reports_unknown_enum_value_with_line_and_column() {
+        let input = "kana_type,category,kana,romaji\nhiragana,seien,あ,a\n";
+        assert!(read_error(input).starts_with("test.csv:2:2: unknown category `seien` (expected one of seion,"));
+
+        // 欄位順序不同時, 回報的是實際的欄位位置
+        let input = "romaji,kana,category,kana_type\na,あ,seion,hiragama\n";
+        assert!(read_error(input).starts_with("test.csv:2:4: unknown kana_type `hiragama`"));
+    }
+
+    #[test]
+    fn skips_comment_lines_but_keeps_line_numbers() {
+        let input = "kana_type,category,kana,romaji\n# 平假名\nhiragana,seion,あ,a\n# 片假名\nkatakana,seion,ア,a\n";
+        let records = read(input).unwrap();
+        let lines: Vec<Option<u64>> = records.iter().map(|record| record.line).collect();
+        assert_eq!(lines, [Some(3), Some(5)]);
+        assert_eq!(records[1].mapping.kana, "ア");
+    }
+
+    #[test]
+    fn keeps_non_empty_extra_columns() {
+        let input = "kana_type,category,kana,romaji,note,strokes\nhiragana,seion,あ,a,,3\n";
+        let records = read(input).unwrap();
+        let extra = &records[0].mapping.extra;
+        assert_eq!(extra.len(), 1);
+        assert_eq!(extra.get("strokes"), Some(&Bson::String("3".to_string())));
+    }
+}
@@ -0,0 +1,121 @@
+use super::{strip_position, write_error, DatasetError, DatasetReader, DatasetWriter, Record, Records};
+use crate::model::KanaMapping;
+use std::io::{BufRead, BufReader, Read, Write};
+
+// JSON 陣列, 整份檔案一次解析
+pub struct JsonReader;
+
+impl DatasetReader for JsonReader {
+    fn read(&self, mut input: Box<dyn Read>, source: &str) -> Result<Records, DatasetError> {
+        let mut text = String::new();
+        input
+            .read_to_string(&mut text)
+            .map_err(|e| DatasetError::new(source, format!("cannot read file: {}", e)))?;
+        let mappings: Vec<KanaMapping> = serde_json::from_str(&text).map_err(|e| {
+            let line = text.lines().nth(e.line().saturating_sub(1)).unwrap_or_default();
+            json_error(source, e, line, None)
+        })?;
+        Ok(Box::new(mappings.into_iter().map(|mapping| Ok(Record { line: None, mapping }))))
+    }
+}
+
+// 每行一筆 JSON 物件, 逐行串流解析, 空行會被略過
+pub struct NdjsonReader;
+
+impl DatasetReader for NdjsonReader {
+    fn read(&self, input: Box<dyn Read>, source: &str) -> Result<Records, DatasetError> {
+        let source = source.to_string();
+        let lines = BufReader::new(input).lines().enumerate();
+        Ok(Box::new(lines.filter_map(move |(index, result)| {
+            let line = index as u64 + 1;
+            let text = match result {
+                Ok(text) => text,
+                Err(e) => return Some(Err(DatasetError::new(&source, e.to_string()).at(Some(line), None))),
+            };
+            if text.trim().is_empty() {
+                return None;
+            }
+            Some(
+                serde_json::from_str(&text)
+                    .map(|mapping| Record { line: Some(line), mapping })
+                    .map_err(|e| json_error(&source, e, &text, Some(line))),
+            )
+        })))
+    }
+}
+
+pub struct JsonWriter;
+
+impl DatasetWriter for JsonWriter {
+    fn write(&self, mappings: &[KanaMapping], output: &mut dyn Write, destination: &str) -> Result<(), DatasetError> {
+        serde_json::to_writer_pretty(&mut *output, mappings).map_err(|e| write_error(destination, e))?;
+        writeln!(output).map_err(|e| write_error(destination, e))
+    }
+}
+
+pub struct NdjsonWriter;
+
+impl DatasetWriter for NdjsonWriter {
+    fn write(&self, mappings: &[KanaMapping], output: &mut dyn Write, destination: &str) -> Result<(), DatasetError> {
+        for mapping in mappings {
+            serde_json::to_writer(&mut *output, mapping).map_err(|e| write_error(destination, e))?;
+            writeln!(output).map_err(|e| write_error(destination, e))?;
+        }
+        Ok(())
+    }
+}
+
+// serde_json 的欄位以位元組計算, 與其他格式一致改以字元計算; text 為錯誤所在的那一行
+fn json_error(source: &str, error: serde_json::Error, text: &str, line: Option<u64>) -> DatasetError {
+    let line = line.unwrap_or(error.line() as u64);
+    let column = text.char_indices().take_while(|(index, _)| *index < error.column()).count();
+    DatasetError::new(source, strip_position(error.to_string())).at(Some(line), Some(column))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::{Category, KanaType};
+    use std::io::Cursor;
+
+    fn read(reader: &dyn DatasetReader, input: &str) -> Result<Vec<Record>, DatasetError> {
+        reader.read(Box::new(Cursor::new(input.to_string().into_bytes())), "test.json")?.collect()
+    }
+
+    #[test]
+    fn reads_a_json_array() {
+        let input = r#"[
+  {"kana_type": "hiragana", "category": "seion", "kana": "あ", "romaji": "a"},
+  {"kana_type": "katakana", "category": "extended_katakana", "kana": "ヴ", "romaji": "vu", "usage": "rare", "note": "loanword"}
+]"#;
+        let records = read(&JsonReader, input).unwrap();
+        assert_eq!(records[0].mapping, KanaMapping::new(KanaType::Hiragana, Category::Seion, "あ", "a"));
+        assert_eq!(records[1].line, None);
+        assert_eq!(records[1].mapping.extra.get("note").and_then(|note| note.as_str()), Some("loanword"));
+    }
+
+    #[test]
+    fn reports_json_errors_at_line_and_column_without_the_serde_suffix() {
+        // あ 佔 3 個位元組, 欄位仍以字元計算
+        let input = "[\n  {\"kana\": \"あ\", \"kana_type\": \"hiragana\", \"category\": \"seien\", \"romaji\": \"a\"}\n]";
+        let error = read(&JsonReader, input).unwrap_err();
+        assert_eq!((error.line, error.column), (Some(2), Some(60)));
+        assert!(error.message.starts_with("unknown variant `seien`, expected one of `seion`,"), "{}", error.message);
+        assert!(!error.message.contains(" at line "), "{}", error.message);
+    }
+
+    #[test]
+    fn ndjson_skips_blank_lines_but_keeps_line_numbers() {
+        let input = "{\"kana_type\": \"hiragana\", \"category\": \"seion\", \"kana\": \"あ\", \"romaji\": \"a\"}\n\n  \n{\"kana_type\": \"hiragana\", \"category\": \"seion\", \"kana\": \"い\", \"romaji\": \"i\"}\n";
+        let records = read(&NdjsonReader, input).unwrap();
+        let lines: Vec<Option<u64>> = records.iter().map(|record| record.line).collect();
+        assert_eq!(lines, [Some(1), Some(4)]);
+        assert_eq!(records[1].mapping.kana, "い");
+    }
+
+    #[test]
+    fn reports_ndjson_errors_at_the_file_line() {
+        let input = "{\"kana_type\": \"hiragana\", \"category\": \"seion\", \"kana\": \"あ\", \"romaji\": \"a\"}\n\n{\"kana_type\": \"hiragana\", \"category\": \"seion\", \"kana\": \"い\"}\n";
+        assert_eq!(read(&NdjsonReader, input).unwrap_err().to_string(), "test.json:3:59: missing field `romaji`");
+    }
+}
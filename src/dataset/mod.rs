@@ -0,0 +1,488 @@
+mod completeness;
+mod delimited;
+mod json;
+mod toml;
+mod yaml;
+
+use crate::model::{Category, KanaMapping, KanaType};
+pub use completeness::{check_youon, standard_youon};
+use std::collections::HashMap;
+use std::fmt;
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::Path;
+use std::str::FromStr;
+
+// 編譯時內嵌的預設資料集
+const BUILTIN_CSV: &str = include_str!("../../data/kana.csv");
+const BUILTIN_SOURCE: &str = "data/kana.csv (builtin)";
+
+#[derive(Debug)]
+pub struct DatasetError {
+    pub source: String,
+    pub line: Option<u64>,
+    pub column: Option<usize>,
+    pub message: String,
+}
+
+impl DatasetError {
+    pub(crate) fn new(source: &str, message: impl Into<String>) -> Self {
+        DatasetError { source: source.to_string(), line: None, column: None, message: message.into() }
+    }
+
+    pub(crate) fn at(mut self, line: Option<u64>, column: Option<usize>) -> Self {
+        self.line = line;
+        self.column = column;
+        self
+    }
+}
+
+impl fmt::Display for DatasetError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.source)?;
+        if let Some(line) = self.line {
+            write!(f, ":{}", line)?;
+            if let Some(column) = self.column {
+                write!(f, ":{}", column)?;
+            }
+        }
+        write!(f, ": {}", self.message)
+    }
+}
+
+impl std::error::Error for DatasetError {}
+
+// 資料集中的一筆資料, line 為來源檔案中的行號 (格式無法提供時為 None)
+#[derive(Debug, Clone)]
+pub struct Record {
+    pub line: Option<u64>,
+    pub mapping: KanaMapping,
+}
+
+pub type Records = Box<dyn Iterator<Item = Result<Record, DatasetError>>>;
+
+// 各種資料格式的解析器, 逐筆產生資料; Dataset 與 load 仍會在寫入前讀入整個資料集
+pub trait DatasetReader {
+    fn read(&self, input: Box<dyn Read>, source: &str) -> Result<Records, DatasetError>;
+}
+
+// 將資料寫成各種格式, 輸出的檔案可以再由對應的 DatasetReader 讀回
+pub trait DatasetWriter {
+    fn write(&self, mappings: &[KanaMapping], output: &mut dyn Write, destination: &str) -> Result<(), DatasetError>;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Csv,
+    Tsv,
+    Json,
+    Ndjson,
+    Yaml,
+    Toml,
+}
+
+impl Format {
+    pub fn from_path(path: &Path) -> Option<Format> {
+        let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+        match extension.as_str() {
+            "csv" => Some(Format::Csv),
+            "tsv" => Some(Format::Tsv),
+            "json" => Some(Format::Json),
+            "ndjson" | "jsonl" => Some(Format::Ndjson),
+            "yaml" | "yml" => Some(Format::Yaml),
+            "toml" => Some(Format::Toml),
+            _ => None,
+        }
+    }
+
+    pub fn reader(self) -> Box<dyn DatasetReader> {
+        match self {
+            Format::Csv => Box::new(delimited::DelimitedReader { delimiter: b',' }),
+            Format::Tsv => Box::new(delimited::DelimitedReader { delimiter: b'\t' }),
+            Format::Json => Box::new(json::JsonReader),
+            Format::Ndjson => Box::new(json::NdjsonReader),
+            Format::Yaml => Box::new(yaml::YamlReader),
+            Format::Toml => Box::new(toml::TomlReader),
+        }
+    }
+
+    pub fn writer(self) -> Box<dyn DatasetWriter> {
+        match self {
+            Format::Csv => Box::new(delimited::DelimitedWriter { delimiter: b',' }),
+            Format::Tsv => Box::new(delimited::DelimitedWriter { delimiter: b'\t' }),
+            Format::Json => Box::new(json::JsonWriter),
+            Format::Ndjson => Box::new(json::NdjsonWriter),
+            Format::Yaml => Box::new(yaml::YamlWriter),
+            Format::Toml => Box::new(toml::TomlWriter),
+        }
+    }
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "csv" => Ok(Format::Csv),
+            "tsv" => Ok(Format::Tsv),
+            "json" => Ok(Format::Json),
+            "ndjson" | "jsonl" => Ok(Format::Ndjson),
+            "yaml" | "yml" => Ok(Format::Yaml),
+            "toml" => Ok(Format::Toml),
+            _ => Err(format!("unknown format `{}` (expected csv, tsv, json, ndjson, yaml or toml)", s)),
+        }
+    }
+}
+
+pub fn builtin() -> Result<Vec<KanaMapping>, DatasetError> {
+    collect(builtin_records()?)
+}
+
+fn builtin_records() -> Result<Records, DatasetError> {
+    let records = Format::Csv.reader().read(Box::new(BUILTIN_CSV.as_bytes()), BUILTIN_SOURCE)?;
+    Ok(validated(records, BUILTIN_SOURCE))
+}
+
+// 載入後的資料集, 保留來源與行號以便回報寫入錯誤的位置
+#[derive(Debug, Clone)]
+pub struct Dataset {
+    pub source: String,
+    pub records: Vec<Record>,
+}
+
+impl Dataset {
+    pub fn builtin() -> Result<Self, DatasetError> {
+        Ok(Dataset { source: BUILTIN_SOURCE.to_string(), records: builtin_records()?.collect::<Result<_, _>>()? })
+    }
+
+    pub fn open(path: &Path, format: Option<Format>) -> Result<Self, DatasetError> {
+        Ok(Dataset { source: path.display().to_string(), records: open(path, format)?.collect::<Result<_, _>>()? })
+    }
+
+    pub fn mappings(&self) -> Vec<KanaMapping> {
+        self.records.iter().map(|record| record.mapping.clone()).collect()
+    }
+
+    // (kana_type, kana) 對應到 `來源:行號`, 沒有行號的格式以第幾筆表示
+    pub fn positions(&self) -> HashMap<(KanaType, String), String> {
+        self.records
+            .iter()
+            .enumerate()
+            .map(|(index, record)| {
+                let position = match record.line {
+                    Some(line) => format!("{}:{}", self.source, line),
+                    None => format!("{}: record {}", self.source, index + 1),
+                };
+                ((record.mapping.kana_type, record.mapping.kana.clone()), position)
+            })
+            .collect()
+    }
+}
+
+// 未指定格式時依副檔名判斷
+pub fn open(path: &Path, format: Option<Format>) -> Result<Records, DatasetError> {
+    let source = path.display().to_string();
+    let format = detect(path, format, &source)?;
+    let file = File::open(path)
+        .map_err(|e| DatasetError::new(&source, format!("cannot open file: {}", e)))?;
+    let records = format.reader().read(Box::new(BufReader::new(file)), &source)?;
+    Ok(validated(records, &source))
+}
+
+fn detect(path: &Path, format: Option<Format>, source: &str) -> Result<Format, DatasetError> {
+    format.or_else(|| Format::from_path(path)).ok_or_else(|| {
+        DatasetError::new(source, "cannot detect the dataset format from the file extension, use --format")
+    })
+}
+
+pub fn load(path: &Path, format: Option<Format>) -> Result<Vec<KanaMapping>, DatasetError> {
+    collect(open(path, format)?)
+}
+
+pub fn validate(mapping: &KanaMapping) -> Result<(), String> {
+    if mapping.kana.trim().is_empty() {
+        return Err("empty value for field `kana`".to_string());
+    }
+    // 促音與長音符號的羅馬拼音必須為空, 其餘假名則必須有羅馬拼音
+    match mapping.role.filter(|role| !role.has_romaji()) {
+        Some(role) if !mapping.romaji.is_empty() => {
+            return Err(format!("romaji must be empty for role `{}`", role));
+        }
+        None if mapping.romaji.trim().is_empty() => return Err("empty value for field `romaji`".to_string()),
+        _ => {}
+    }
+    if !mapping.romaji.bytes().all(|b| b.is_ascii_lowercase()) {
+        return Err(format!("romaji `{}` must only contain lowercase ASCII letters", mapping.romaji));
+    }
+    match (mapping.role, mapping.category) {
+        (Some(role), category) if role.category() != category => {
+            return Err(format!("role `{}` requires category `{}`", role, role.category()));
+        }
+        (None, category) if category.requires_role() => {
+            return Err(format!("category `{}` requires a `role`", mapping.category));
+        }
+        _ => {}
+    }
+    // 外來語片假名只有 (全形或半形) 片假名寫法, 並且必須標明常用或少用
+    match (mapping.category, mapping.usage) {
+        (Category::ExtendedKatakana, _) if mapping.kana_type == KanaType::Hiragana => {
+            return Err(format!(
+                "category `{}` requires kana_type `{}` or `{}`",
+                mapping.category,
+                KanaType::Katakana,
+                KanaType::HalfwidthKatakana
+            ));
+        }
+        (Category::ExtendedKatakana, None) => {
+            return Err(format!("category `{}` requires a `usage`", mapping.category));
+        }
+        (Category::ExtendedKatakana, Some(_)) | (_, None) => {}
+        (_, Some(_)) => {
+            return Err(format!("`usage` is only allowed for category `{}`", Category::ExtendedKatakana));
+        }
+    }
+    // 半形片假名必須連結到全形寫法, 全形寫法本身不可再含半形字元
+    match (mapping.kana_type, &mapping.fullwidth) {
+        (KanaType::HalfwidthKatakana, None) => {
+            return Err(format!("kana_type `{}` requires a `fullwidth`", KanaType::HalfwidthKatakana));
+        }
+        (KanaType::HalfwidthKatakana, Some(fullwidth)) if fullwidth.chars().any(is_halfwidth) => {
+            return Err(format!("fullwidth `{}` must not contain half-width katakana", fullwidth));
+        }
+        (KanaType::HalfwidthKatakana, Some(_)) | (_, None) => {}
+        (_, Some(_)) => {
+            return Err(format!("`fullwidth` is only allowed for kana_type `{}`", KanaType::HalfwidthKatakana));
+        }
+    }
+    // 古假名須標明現代寫法 (疊字符號的讀音取決於前一個假名, 因此沒有), 並且不出現在初學者練習中
+    if mapping.category == Category::Archaic {
+        if mapping.beginner != Some(false) {
+            return Err(format!("category `{}` requires `beginner` to be false", mapping.category));
+        }
+        if mapping.role.is_none() && mapping.modern_kana.is_none() {
+            return Err(format!("category `{}` requires a `modern_kana`", mapping.category));
+        }
+    }
+    match &mapping.modern_kana {
+        Some(_) if mapping.category != Category::Archaic || mapping.role.is_some() => {
+            Err(format!("`modern_kana` is only allowed for category `{}` without a role", Category::Archaic))
+        }
+        _ => Ok(()),
+    }
+}
+
+// 半形片假名與符號位於 U+FF61 至 U+FF9F
+fn is_halfwidth(c: char) -> bool {
+    ('\u{ff61}'..='\u{ff9f}').contains(&c)
+}
+
+// 逐筆檢查欄位內容, 並確認 (kana_type, kana) 在資料集中沒有重複
+fn validated(records: Records, source: &str) -> Records {
+    let source = source.to_string();
+    let mut seen: HashMap<(KanaType, String), String> = HashMap::new();
+    Box::new(records.enumerate().map(move |(index, result)| {
+        let record = result?;
+        let position = match record.line {
+            Some(line) => format!("line {}", line),
+            None => format!("record {}", index + 1),
+        };
+        let error = |message: String| match record.line {
+            Some(line) => DatasetError::new(&source, message).at(Some(line), None),
+            None => DatasetError::new(&source, format!("record {}: {}", index + 1, message)),
+        };
+
+        validate(&record.mapping).map_err(error)?;
+
+        let (kana_type, kana) = record.mapping.key();
+        if let Some(first) = seen.get(&(kana_type, kana.to_string())) {
+            return Err(error(format!("duplicate kana `{}` for kana_type `{}` (first defined at {})", kana, kana_type, first)));
+        }
+        seen.insert((kana_type, kana.to_string()), position);
+        Ok(record)
+    }))
+}
+
+// serde 系列解析器的錯誤訊息結尾帶有位置資訊, 改由 DatasetError 統一顯示
+pub(crate) fn strip_position(message: String) -> String {
+    match message.rfind(" at line ") {
+        Some(index) => message[..index].to_string(),
+        None => message,
+    }
+}
+
+// 未指定格式時依副檔名判斷
+pub fn save(mappings: &[KanaMapping], path: &Path, format: Option<Format>) -> Result<(), DatasetError> {
+    let destination = path.display().to_string();
+    let format = detect(path, format, &destination)?;
+    let file = File::create(path).map_err(|e| DatasetError::new(&destination, format!("cannot create file: {}", e)))?;
+    write(mappings, format, &mut BufWriter::new(file), &destination)
+}
+
+pub fn write(mappings: &[KanaMapping], format: Format, output: &mut dyn Write, destination: &str) -> Result<(), DatasetError> {
+    format.writer().write(mappings, output, destination)?;
+    output.flush().map_err(|e| write_error(destination, e))
+}
+
+pub(crate) fn write_error(destination: &str, error: impl fmt::Display) -> DatasetError {
+    DatasetError::new(destination, format!("cannot write dataset: {}", error))
+}
+
+fn collect(records: Records) -> Result<Vec<KanaMapping>, DatasetError> {
+    records.map(|result| result.map(|record| record.mapping)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::{Role, Usage};
+    use mongodb::bson::Bson;
+
+    // 外来語の表記 第1表與第2表中以片假名寫出的外來語音
+    const TABLE_1: [&str; 13] = ["シェ", "チェ", "ツァ", "ツェ", "ツォ", "ティ", "ファ", "フィ", "フェ", "フォ", "ジェ", "ディ", "デュ"];
+    const TABLE_2: [&str; 20] = [
+        "イェ", "ウィ", "ウェ", "ウォ", "クァ", "クィ", "クェ", "クォ", "ツィ", "トゥ", "グァ", "ドゥ", "ヴァ", "ヴィ", "ヴ", "ヴェ", "ヴォ", "テュ",
+        "フュ", "ヴュ",
+    ];
+
+    const FORMATS: [Format; 6] = [Format::Csv, Format::Tsv, Format::Json, Format::Ndjson, Format::Yaml, Format::Toml];
+
+    fn round_trip(mappings: &[KanaMapping], format: Format) -> Vec<KanaMapping> {
+        let mut output = Vec::new();
+        write(mappings, format, &mut output, "out").unwrap();
+        collect(format.reader().read(Box::new(std::io::Cursor::new(output)), "out").unwrap()).unwrap()
+    }
+
+    #[test]
+    fn every_format_reads_back_what_it_writes() {
+        let mut mappings = builtin().unwrap();
+        let note = [("note".to_string(), Bson::String("ア行".to_string()))].into_iter().collect();
+        mappings[0].extra = note;
+        for format in FORMATS {
+            assert_eq!(round_trip(&mappings, format), mappings, "{:?}", format);
+        }
+    }
+
+    // CSV 與 TSV 沒有型別, 非字串的額外欄位會以字串讀回; 其他格式保留型別 (TOML 的整數一律為 64 位元)
+    #[test]
+    fn delimited_formats_read_back_non_string_extra_values_as_strings() {
+        let extra = [("strokes".to_string(), Bson::Int32(3)), ("common".to_string(), Bson::Boolean(true))];
+        let mappings = [KanaMapping { extra: extra.into_iter().collect(), ..KanaMapping::new(KanaType::Hiragana, Category::Seion, "あ", "a") }];
+        for format in FORMATS {
+            let extra = round_trip(&mappings, format).remove(0).extra;
+            let expected = match format {
+                Format::Csv | Format::Tsv => (Bson::String("3".to_string()), Bson::String("true".to_string())),
+                Format::Toml => (Bson::Int64(3), Bson::Boolean(true)),
+                _ => (Bson::Int32(3), Bson::Boolean(true)),
+            };
+            assert_eq!((extra["strokes"].clone(), extra["common"].clone()), expected, "{:?}", format);
+        }
+    }
+
+    #[test]
+    fn strip_position_removes_only_the_trailing_location() {
+        assert_eq!(strip_position("missing field `romaji` at line 3 column 59".to_string()), "missing field `romaji`");
+        assert_eq!(strip_position("invalid type: map".to_string()), "invalid type: map");
+    }
+
+    #[test]
+    fn extended_katakana_requires_a_usage() {
+        let ti = KanaMapping::new(KanaType::Katakana, Category::ExtendedKatakana, "ティ", "ti");
+        assert_eq!(validate(&ti), Err("category `extended_katakana` requires a `usage`".to_string()));
+        assert_eq!(validate(&KanaMapping { usage: Some(Usage::Standard), ..ti }), Ok(()));
+
+        let ti = KanaMapping { usage: Some(Usage::Standard), ..KanaMapping::new(KanaType::Hiragana, Category::ExtendedKatakana, "てぃ", "ti") };
+        assert_eq!(
+            validate(&ti),
+            Err("category `extended_katakana` requires kana_type `katakana` or `halfwidth_katakana`".to_string())
+        );
+    }
+
+    #[test]
+    fn usage_is_rejected_outside_extended_katakana() {
+        let a = KanaMapping { usage: Some(Usage::Rare), ..KanaMapping::new(KanaType::Katakana, Category::Seion, "ア", "a") };
+        assert_eq!(validate(&a), Err("`usage` is only allowed for category `extended_katakana`".to_string()));
+    }
+
+    #[test]
+    fn halfwidth_katakana_requires_a_fullwidth_without_halfwidth_characters() {
+        let ka = KanaMapping::new(KanaType::HalfwidthKatakana, Category::Seion, "ｶ", "ka");
+        assert_eq!(validate(&ka), Err("kana_type `halfwidth_katakana` requires a `fullwidth`".to_string()));
+        assert_eq!(validate(&KanaMapping { fullwidth: Some("カ".to_string()), ..ka.clone() }), Ok(()));
+
+        let ga = KanaMapping { fullwidth: Some("カﾞ".to_string()), ..KanaMapping::new(KanaType::HalfwidthKatakana, Category::DakuonHandaon, "ｶﾞ", "ga") };
+        assert_eq!(validate(&ga), Err("fullwidth `カﾞ` must not contain half-width katakana".to_string()));
+    }
+
+    #[test]
+    fn fullwidth_is_rejected_for_other_kana_types() {
+        for kana_type in [KanaType::Hiragana, KanaType::Katakana] {
+            let ka = KanaMapping { fullwidth: Some("カ".to_string()), ..KanaMapping::new(kana_type, Category::Seion, "カ", "ka") };
+            assert_eq!(validate(&ka), Err("`fullwidth` is only allowed for kana_type `halfwidth_katakana`".to_string()));
+        }
+    }
+
+    #[test]
+    fn builtin_halfwidth_katakana_matches_its_fullwidth_kana() {
+        let mappings = builtin().unwrap();
+        let katakana: HashMap<&str, &KanaMapping> = mappings
+            .iter()
+            .filter(|mapping| mapping.kana_type == KanaType::Katakana)
+            .map(|mapping| (mapping.kana.as_str(), mapping))
+            .collect();
+        let halfwidth = mappings.iter().filter(|mapping| mapping.kana_type == KanaType::HalfwidthKatakana);
+        for mapping in halfwidth {
+            // ﾞ 與 ﾟ 對應的 ゛ 與 ゜ 不在資料集中
+            let Some(target) = katakana.get(mapping.fullwidth.as_deref().unwrap()) else { continue };
+            assert_eq!(
+                (mapping.category, mapping.usage, mapping.role, &mapping.romaji),
+                (target.category, target.usage, target.role, &target.romaji),
+                "{} does not match {}",
+                mapping.kana,
+                target.kana
+            );
+        }
+    }
+
+    #[test]
+    fn archaic_kana_require_a_modern_kana_and_beginner_false() {
+        let wi = KanaMapping::new(KanaType::Hiragana, Category::Archaic, "ゐ", "i");
+        assert_eq!(validate(&wi), Err("category `archaic` requires `beginner` to be false".to_string()));
+
+        let wi = KanaMapping { beginner: Some(false), ..wi };
+        assert_eq!(validate(&wi), Err("category `archaic` requires a `modern_kana`".to_string()));
+        assert_eq!(validate(&KanaMapping { modern_kana: Some("い".to_string()), ..wi }), Ok(()));
+    }
+
+    #[test]
+    fn iteration_marks_have_no_modern_kana() {
+        let mark = KanaMapping {
+            role: Some(Role::IterationMark),
+            beginner: Some(false),
+            ..KanaMapping::new(KanaType::Hiragana, Category::Archaic, "ゝ", "")
+        };
+        assert_eq!(validate(&mark), Ok(()));
+
+        let mark = KanaMapping { modern_kana: Some("い".to_string()), ..mark };
+        assert_eq!(validate(&mark), Err("`modern_kana` is only allowed for category `archaic` without a role".to_string()));
+    }
+
+    #[test]
+    fn modern_kana_is_rejected_outside_archaic() {
+        let i = KanaMapping { modern_kana: Some("い".to_string()), ..KanaMapping::new(KanaType::Hiragana, Category::Seion, "い", "i") };
+        assert_eq!(validate(&i), Err("`modern_kana` is only allowed for category `archaic` without a role".to_string()));
+    }
+
+    #[test]
+    fn builtin_dataset_has_both_gairaigo_tables() {
+        let mappings = builtin().unwrap();
+        let listed = |usage: Usage| -> Vec<&str> {
+            mappings
+                .iter()
+                .filter(|mapping| mapping.kana_type == KanaType::Katakana && mapping.usage == Some(usage))
+                .map(|mapping| mapping.kana.as_str())
+                .collect()
+        };
+        assert_eq!(listed(Usage::Standard), TABLE_1);
+        assert_eq!(listed(Usage::Rare), TABLE_2);
+    }
+}
@@ -0,0 +1,79 @@
+use super::{write_error, DatasetError, DatasetReader, DatasetWriter, Record, Records};
+use crate::model::KanaMapping;
+use serde::{Deserialize, Serialize};
+use std::io::{Read, Write};
+
+// TOML 沒有頂層陣列, 資料放在 [[kana_mappings]] 表格陣列中
+#[derive(Deserialize)]
+struct TomlDataset {
+    kana_mappings: Vec<KanaMapping>,
+}
+
+#[derive(Serialize)]
+struct TomlDatasetRef<'a> {
+    kana_mappings: &'a [KanaMapping],
+}
+
+pub struct TomlReader;
+
+impl DatasetReader for TomlReader {
+    fn read(&self, mut input: Box<dyn Read>, source: &str) -> Result<Records, DatasetError> {
+        let mut text = String::new();
+        input
+            .read_to_string(&mut text)
+            .map_err(|e| DatasetError::new(source, format!("cannot read file: {}", e)))?;
+
+        let dataset: TomlDataset = ::toml::from_str(&text).map_err(|e| {
+            let (line, column) = match e.span() {
+                Some(span) => {
+                    let before = &text[..span.start];
+                    let line = before.matches('\n').count() as u64 + 1;
+                    let column = before[before.rfind('\n').map_or(0, |index| index + 1)..].chars().count() + 1;
+                    (Some(line), Some(column))
+                }
+                None => (None, None),
+            };
+            DatasetError::new(source, e.message()).at(line, column)
+        })?;
+        Ok(Box::new(dataset.kana_mappings.into_iter().map(|mapping| Ok(Record { line: None, mapping }))))
+    }
+}
+
+pub struct TomlWriter;
+
+impl DatasetWriter for TomlWriter {
+    fn write(&self, mappings: &[KanaMapping], output: &mut dyn Write, destination: &str) -> Result<(), DatasetError> {
+        let text = ::toml::to_string(&TomlDatasetRef { kana_mappings: mappings }).map_err(|e| write_error(destination, e))?;
+        output.write_all(text.as_bytes()).map_err(|e| write_error(destination, e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::{Category, KanaType};
+    use std::io::Cursor;
+
+    fn read(input: &str) -> Result<Vec<Record>, DatasetError> {
+        TomlReader.read(Box::new(Cursor::new(input.to_string().into_bytes())), "test.toml")?.collect()
+    }
+
+    #[test]
+    fn reads_kana_mappings_tables() {
+        let input = "[[kana_mappings]]\nkana_type = \"hiragana\"\ncategory = \"seion\"\nkana = \"あ\"\nromaji = \"a\"\n\n[[kana_mappings]]\nkana_type = \"halfwidth_katakana\"\ncategory = \"seion\"\nkana = \"ｱ\"\nromaji = \"a\"\nfullwidth = \"ア\"\n";
+        let records = read(input).unwrap();
+        assert_eq!(records[0].mapping, KanaMapping::new(KanaType::Hiragana, Category::Seion, "あ", "a"));
+        assert_eq!(records[1].mapping.fullwidth.as_deref(), Some("ア"));
+    }
+
+    // 行號與欄位由錯誤範圍的位元組位置換算, 欄位以字元計算
+    #[test]
+    fn reports_toml_errors_at_line_and_character_column() {
+        let input = "[[kana_mappings]]\nkana_type = \"hiragana\"\ncategory = \"seion\"\nkana = \"あ\"\nromaji = \"a\"\n\n[[kana_mappings]]\nkana = \"い\"; romaji = \"i\"\n";
+        assert_eq!(read(input).unwrap_err().to_string(), "test.toml:8:11: expected newline, `#`");
+        let input = "[[kana_mappings]]\nkana_type = \"hiragama\"\ncategory = \"seion\"\nkana = \"あ\"\nromaji = \"a\"\n";
+        let error = read(input).unwrap_err();
+        assert_eq!((error.line, error.column), (Some(2), Some(13)));
+        assert!(error.message.starts_with("unknown variant `hiragama`"), "{}", error.message);
+    }
+}
@@ -0,0 +1,56 @@
+use super::{strip_position, write_error, DatasetError, DatasetReader, DatasetWriter, Record, Records};
+use crate::model::KanaMapping;
+use std::io::{Read, Write};
+
+// 頂層為資料序列的 YAML 文件
+pub struct YamlReader;
+
+impl DatasetReader for YamlReader {
+    fn read(&self, input: Box<dyn Read>, source: &str) -> Result<Records, DatasetError> {
+        let mappings: Vec<KanaMapping> = serde_yaml::from_reader(input).map_err(|e| {
+            let location = e.location();
+            DatasetError::new(source, strip_position(e.to_string())).at(
+                location.as_ref().map(|location| location.line() as u64),
+                location.as_ref().map(|location| location.column()),
+            )
+        })?;
+        Ok(Box::new(mappings.into_iter().map(|mapping| Ok(Record { line: None, mapping }))))
+    }
+}
+
+pub struct YamlWriter;
+
+impl DatasetWriter for YamlWriter {
+    fn write(&self, mappings: &[KanaMapping], output: &mut dyn Write, destination: &str) -> Result<(), DatasetError> {
+        serde_yaml::to_writer(output, mappings).map_err(|e| write_error(destination, e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::{Category, KanaType};
+    use std::io::Cursor;
+
+    fn read(input: &str) -> Result<Vec<Record>, DatasetError> {
+        YamlReader.read(Box::new(Cursor::new(input.to_string().into_bytes())), "test.yaml")?.collect()
+    }
+
+    #[test]
+    fn reads_a_yaml_sequence() {
+        let input = "- kana_type: hiragana\n  category: seion\n  kana: あ\n  romaji: a\n- kana_type: hiragana\n  category: archaic\n  kana: ゐ\n  romaji: i\n  modern_kana: い\n  beginner: false\n";
+        let records = read(input).unwrap();
+        assert_eq!(records[0].mapping, KanaMapping::new(KanaType::Hiragana, Category::Seion, "あ", "a"));
+        assert_eq!(records[1].mapping.modern_kana.as_deref(), Some("い"));
+        assert_eq!(records[1].mapping.beginner, Some(false));
+    }
+
+    #[test]
+    fn reports_yaml_errors_at_line_and_column() {
+        let input = "- kana_type: hiragana\n  category: seion\n  kana: あ\n  romaji: a\n- kana_type: hiragama\n  category: seion\n  kana: い\n  romaji: i\n";
+        assert_eq!(
+            read(input).unwrap_err().to_string(),
+            "test.yaml:5:14: .[1].kana_type: unknown variant `hiragama`, expected one of `hiragana`, `katakana`, `halfwidth_katakana`"
+        );
+    }
+}
@@ -0,0 +1,117 @@
+use crate::dataset::DatasetError;
+use std::fmt;
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+// 結束代碼, 2 與 clap 的參數錯誤相同
+pub const EXIT_USAGE: i32 = 2;
+pub const EXIT_CONNECTION: i32 = 3;
+pub const EXIT_VALIDATION: i32 = 4;
+pub const EXIT_DATASET: i32 = 5;
+pub const EXIT_CONFIG: i32 = 6;
+pub const EXIT_WRITE: i32 = 7;
+pub const EXIT_PERMISSION: i32 = 8;
+
+#[derive(Debug)]
+pub enum Error {
+    // 缺少或無效的設定
+    Config(ConfigError),
+    // 無法連線或讀取 MongoDB
+    Connection(mongodb::error::Error),
+    // 集合內容不符合預期, 例如 verify 發現差異或文件無法解析
+    Validation(String),
+    // 資料集檔案無法讀取或內容有誤
+    Dataset(DatasetError),
+    // 寫入集合、建立索引或 validator 失敗
+    Write(mongodb::error::Error),
+    // 部分文件被伺服器拒絕寫入, 例如違反 validator 或唯一索引
+    Rejected(String),
+    // 命令列參數的組合不正確
+    Usage(String),
+    // 使用者缺少匯入所需的權限
+    Permission(String),
+}
+
+// 一次列出所有缺少與無效的設定, 而不是遇到第一個就停止
+#[derive(Debug, Default)]
+pub struct ConfigError {
+    pub missing: Vec<String>,
+    pub invalid: Vec<String>,
+    pub hint: Option<String>,
+}
+
+impl ConfigError {
+    pub fn is_empty(&self) -> bool {
+        self.missing.is_empty() && self.invalid.is_empty()
+    }
+}
+
+impl Error {
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            Error::Config(_) => EXIT_CONFIG,
+            Error::Connection(_) => EXIT_CONNECTION,
+            Error::Validation(_) => EXIT_VALIDATION,
+            Error::Dataset(_) => EXIT_DATASET,
+            Error::Write(_) | Error::Rejected(_) => EXIT_WRITE,
+            Error::Usage(_) => EXIT_USAGE,
+            Error::Permission(_) => EXIT_PERMISSION,
+        }
+    }
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut parts = Vec::new();
+        if !self.missing.is_empty() {
+            parts.push(format!("missing {}", self.missing.join(", ")));
+        }
+        parts.extend(self.invalid.iter().cloned());
+        write!(f, "{}", parts.join("; "))?;
+        if let Some(hint) = &self.hint {
+            write!(f, " ({})", hint)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // 只顯示 driver 錯誤的種類, 省略 labels 等除錯資訊
+        match self {
+            Error::Config(e) => write!(f, "configuration error: {}", e),
+            Error::Connection(e) => write!(f, "cannot connect to MongoDB: {}", e.kind),
+            Error::Validation(message) => write!(f, "validation failed: {}", message),
+            Error::Dataset(e) => write!(f, "invalid dataset: {}", e),
+            Error::Write(e) => write!(f, "write failed: {}", e.kind),
+            Error::Rejected(message) => write!(f, "write failed: {}", message),
+            Error::Usage(message) => write!(f, "{}", message),
+            Error::Permission(message) => write!(f, "insufficient privileges: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Config(e) => Some(e),
+            Error::Connection(e) | Error::Write(e) => Some(e),
+            Error::Dataset(e) => Some(e),
+            Error::Validation(_) | Error::Rejected(_) | Error::Usage(_) | Error::Permission(_) => None,
+        }
+    }
+}
+
+impl From<ConfigError> for Error {
+    fn from(e: ConfigError) -> Self {
+        Error::Config(e)
+    }
+}
+
+impl From<DatasetError> for Error {
+    fn from(e: DatasetError) -> Self {
+        Error::Dataset(e)
+    }
+}
@@ -0,0 +1,234 @@
+use crate::connection::{self, ServerInfo};
+use mongodb::{
+    bson::{doc, Document},
+    Client,
+};
+use std::fmt;
+use std::time::{Duration, Instant};
+
+// check 子命令的連線狀態報告
+#[derive(Debug, Clone)]
+pub struct HealthReport {
+    pub latency: Duration,
+    pub topology: Topology,
+    pub primary: Option<String>,
+    pub server: ServerInfo,
+    pub database: String,
+    pub collection: String,
+    // 未驗證身分 (例如未啟用 access control 的伺服器) 或 connectionStatus 失敗時無法判斷權限, 為 None
+    pub privileges: Option<Privileges>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Topology {
+    Standalone,
+    ReplicaSet(String),
+    Sharded,
+}
+
+// import 會執行的動作: 讀取現況、upsert、刪除重複或多餘的文件與建立索引
+pub const IMPORT_ACTIONS: [&str; 6] = ["find", "insert", "update", "remove", "createIndex", "listIndexes"];
+// 只有 import --install-validator 需要, 內建的 readWrite 角色沒有 collMod
+pub const VALIDATOR_ACTIONS: [&str; 2] = ["collMod", "createCollection"];
+
+// 各動作是否獲得授權, 依 IMPORT_ACTIONS 與 VALIDATOR_ACTIONS 的順序
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Privileges {
+    pub actions: Vec<(&'static str, bool)>,
+}
+
+impl Privileges {
+    fn missing_of(&self, actions: &[&str]) -> Vec<&'static str> {
+        self.actions.iter().filter(|(action, granted)| !granted && actions.contains(action)).map(|(action, _)| *action).collect()
+    }
+
+    // import 缺少的權限
+    pub fn missing(&self) -> Vec<&'static str> {
+        self.missing_of(&IMPORT_ACTIONS)
+    }
+
+    // import --install-validator 另外缺少的權限
+    pub fn missing_for_validator(&self) -> Vec<&'static str> {
+        self.missing_of(&VALIDATOR_ACTIONS)
+    }
+}
+
+impl fmt::Display for Topology {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Topology::Standalone => write!(f, "standalone"),
+            Topology::ReplicaSet(name) => write!(f, "replica set {}", name),
+            Topology::Sharded => write!(f, "sharded cluster"),
+        }
+    }
+}
+
+impl fmt::Display for HealthReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let yes_no = |granted: bool| if granted { "yes" } else { "no" };
+        writeln!(f, "latency: {:.1} ms", self.latency.as_secs_f64() * 1000.0)?;
+        writeln!(f, "topology: {}", self.topology)?;
+        writeln!(f, "primary: {}", self.primary.as_deref().unwrap_or("n/a"))?;
+        writeln!(f, "{}", self.server)?;
+        write!(f, "privileges on {}.{}: ", self.database, self.collection)?;
+        match &self.privileges {
+            Some(privileges) => {
+                let actions: Vec<String> =
+                    privileges.actions.iter().map(|(action, granted)| format!("{} {}", action, yes_no(*granted))).collect();
+                write!(f, "{}", actions.join(", "))?;
+                if !privileges.missing_for_validator().is_empty() {
+                    write!(f, " (collMod and createCollection are only needed for import --install-validator)")?;
+                }
+                Ok(())
+            }
+            None => write!(f, "unknown (not authenticated, or connectionStatus unavailable)"),
+        }
+    }
+}
+
+// 連線後再 ping 一次量測往返延遲, 再以 hello 與 connectionStatus 取得拓撲與權限
+pub async fn check(client: &Client, database: &str, collection: &str) -> mongodb::error::Result<HealthReport> {
+    let admin = client.database("admin");
+
+    let started = Instant::now();
+    admin.run_command(doc! {"ping": 1}, None).await?;
+    let latency = started.elapsed();
+
+    let hello = admin.run_command(doc! {"hello": 1}, None).await?;
+    let topology = parse_topology(&hello);
+    let primary = hello.get_str("primary").ok().map(str::to_string);
+
+    // connectionStatus 不在 Stable API v1 中, strict 模式下會失敗, 與 server_info 相同地改報 unknown
+    let status = admin.run_command(doc! {"connectionStatus": 1, "showPrivileges": true}, None).await;
+    let privileges = status_privileges(status, database, collection);
+
+    Ok(HealthReport {
+        latency,
+        topology,
+        primary,
+        server: connection::server_info(client).await,
+        database: database.to_string(),
+        collection: collection.to_string(),
+        privileges,
+    })
+}
+
+// transaction 只能在 replica set 或 sharded cluster 上使用
+pub async fn topology(client: &Client) -> mongodb::error::Result<Topology> {
+    let hello = client.database("admin").run_command(doc! {"hello": 1}, None).await?;
+    Ok(parse_topology(&hello))
+}
+
+fn parse_topology(hello: &Document) -> Topology {
+    match hello.get_str("setName") {
+        Ok(name) => Topology::ReplicaSet(name.to_string()),
+        Err(_) if hello.get_str("msg") == Ok("isdbgrid") => Topology::Sharded,
+        Err(_) => Topology::Standalone,
+    }
+}
+
+fn status_privileges(
+    status: mongodb::error::Result<Document>,
+    database: &str,
+    collection: &str,
+) -> Option<Privileges> {
+    let auth_info = status.ok()?.get_document("authInfo").cloned().unwrap_or_default();
+    parse_privileges(&auth_info, database, collection)
+}
+
+// connectionStatus 的 authInfo; 未驗證身分時為 None
+fn parse_privileges(auth_info: &Document, database: &str, collection: &str) -> Option<Privileges> {
+    let authenticated = auth_info.get_array("authenticatedUsers").is_ok_and(|users| !users.is_empty());
+    authenticated.then(|| {
+        let granted: Vec<&Document> = auth_info
+            .get_array("authenticatedUserPrivileges")
+            .map(|privileges| privileges.iter().filter_map(|privilege| privilege.as_document()).collect())
+            .unwrap_or_default();
+        let allows = |action: &str| {
+            granted.iter().any(|privilege| {
+                let covers = privilege.get_document("resource").is_ok_and(|resource| covers(resource, database, collection));
+                covers && privilege.get_array("actions").is_ok_and(|actions| actions.iter().any(|a| a.as_str() == Some(action)))
+            })
+        };
+        let actions = IMPORT_ACTIONS.into_iter().chain(VALIDATOR_ACTIONS).map(|action| (action, allows(action))).collect();
+        Privileges { actions }
+    })
+}
+
+// resource 的 db 或 collection 為空字串時代表全部
+fn covers(resource: &Document, database: &str, collection: &str) -> bool {
+    if resource.get_bool("anyResource") == Ok(true) {
+        return true;
+    }
+    match (resource.get_str("db"), resource.get_str("collection")) {
+        (Ok(db), Ok(coll)) => (db.is_empty() || db == database) && (coll.is_empty() || coll == collection),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mongodb::error::{CommandError, ErrorKind};
+
+    fn auth_info(actions: &[&str]) -> Document {
+        doc! {
+            "authenticatedUsers": [{ "user": "importer", "db": "admin" }],
+            "authenticatedUserPrivileges": [
+                { "resource": { "db": "jp_syllabaries", "collection": "" }, "actions": actions },
+                { "resource": { "db": "other", "collection": "" }, "actions": ["collMod"] },
+            ],
+        }
+    }
+
+    #[test]
+    fn read_write_role_can_import_but_not_install_the_validator() {
+        let read_write = [
+            "find", "insert", "update", "remove", "createIndex", "dropIndex", "listIndexes", "listCollections", "createCollection",
+        ];
+        let privileges = parse_privileges(&auth_info(&read_write), "jp_syllabaries", "kana_mappings").unwrap();
+        assert!(privileges.missing().is_empty());
+        assert_eq!(privileges.missing_for_validator(), ["collMod"]);
+    }
+
+    #[test]
+    fn read_role_lacks_every_write_action() {
+        let privileges = parse_privileges(&auth_info(&["find", "listIndexes"]), "jp_syllabaries", "kana_mappings").unwrap();
+        assert_eq!(privileges.missing(), ["insert", "update", "remove", "createIndex"]);
+    }
+
+    #[test]
+    fn unauthenticated_connection_has_unknown_privileges() {
+        assert_eq!(parse_privileges(&doc! { "authenticatedUsers": [] }, "jp_syllabaries", "kana_mappings"), None);
+    }
+
+    #[test]
+    fn failed_connection_status_reports_unknown_privileges() {
+        // Stable API strict 模式拒絕 connectionStatus 時的錯誤
+        let error: CommandError = mongodb::bson::from_document(doc! {
+            "code": 323,
+            "codeName": "APIStrictError",
+            "errmsg": "Provided apiStrict:true, but the command connectionStatus is not in API Version 1",
+        })
+        .unwrap();
+        let error = mongodb::error::Error::from(ErrorKind::Command(error));
+        assert_eq!(status_privileges(Err(error), "jp_syllabaries", "kana_mappings"), None);
+
+        let status = doc! { "authInfo": auth_info(&IMPORT_ACTIONS), "ok": 1 };
+        let privileges = status_privileges(Ok(status), "jp_syllabaries", "kana_mappings").unwrap();
+        assert!(privileges.missing().is_empty());
+
+        let report = HealthReport {
+            latency: Duration::from_millis(2),
+            topology: Topology::Standalone,
+            primary: None,
+            server: ServerInfo::default(),
+            database: "jp_syllabaries".to_string(),
+            collection: "kana_mappings".to_string(),
+            privileges: None,
+        };
+        let report = report.to_string();
+        assert!(report.starts_with("latency: 2.0 ms\ntopology: standalone\n"), "{}", report);
+        assert!(report.ends_with("privileges on jp_syllabaries.kana_mappings: unknown (not authenticated, or connectionStatus unavailable)"));
+    }
+}
@@ -0,0 +1,498 @@
+use crate::config::DEFAULT_BATCH_SIZE;
+use crate::model::{KanaMapping, KanaType, OPTIONAL_FIELDS};
+use crate::plan;
+use crate::retry::RetryPolicy;
+use mongodb::{
+    bson::{self, doc, oid::ObjectId, Bson, Document},
+    error::{
+        BulkWriteError, BulkWriteFailure, ErrorKind, WriteConcernError, WriteFailure, UNKNOWN_TRANSACTION_COMMIT_RESULT,
+    },
+    options::{InsertManyOptions, ReadPreference, SelectionCriteria, TransactionOptions},
+    ClientSession, Collection,
+};
+use serde::Deserialize;
+use std::fmt;
+use std::str::FromStr;
+
+// E11000 duplicate key error
+const DUPLICATE_KEY: i32 = 11000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImportMode {
+    // 直接 insert_many, 重複執行會產生重複的資料
+    Insert,
+    // 以 (kana_type, kana) 為鍵 upsert, 可重複執行
+    Upsert,
+}
+
+impl FromStr for ImportMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "insert" => Ok(ImportMode::Insert),
+            "upsert" => Ok(ImportMode::Upsert),
+            _ => Err(format!("unknown import mode `{}` (expected insert or upsert)", s)),
+        }
+    }
+}
+
+// 寫入選項; ordered 為 false 時個別文件寫入失敗不會中斷其餘的寫入
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ImportOptions {
+    pub mode: ImportMode,
+    pub prune: bool,
+    pub batch_size: usize,
+    pub ordered: bool,
+    pub retry: RetryPolicy,
+}
+
+impl Default for ImportOptions {
+    fn default() -> Self {
+        ImportOptions {
+            mode: ImportMode::Upsert,
+            prune: false,
+            batch_size: DEFAULT_BATCH_SIZE,
+            ordered: true,
+            retry: RetryPolicy::default(),
+        }
+    }
+}
+
+// 單一文件的寫入錯誤, 例如違反 validator 或唯一索引; 以 (kana_type, kana) 對應回資料集的行號
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DocumentError {
+    pub kana_type: KanaType,
+    pub kana: String,
+    pub code: i32,
+    pub message: String,
+}
+
+impl DocumentError {
+    fn new(mapping: &KanaMapping, code: i32, message: &str) -> Self {
+        DocumentError { kana_type: mapping.kana_type, kana: mapping.kana.clone(), code, message: message.to_string() }
+    }
+}
+
+impl fmt::Display for DocumentError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} `{}`: {} (code {})", self.kana_type, self.kana, self.message, self.code)
+    }
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct ImportSummary {
+    pub inserted: u64,
+    pub updated: u64,
+    pub deleted: u64,
+    pub unchanged: u64,
+    pub failed: Vec<DocumentError>,
+}
+
+impl ImportSummary {
+    // ordered 寫入遇到第一個失敗的文件後即停止
+    fn stopped(&self, options: &ImportOptions) -> bool {
+        options.ordered && !self.failed.is_empty()
+    }
+
+    fn add(&mut self, other: ImportSummary) {
+        self.inserted += other.inserted;
+        self.updated += other.updated;
+        self.deleted += other.deleted;
+        self.unchanged += other.unchanged;
+        self.failed.extend(other.failed);
+    }
+}
+
+impl fmt::Display for ImportSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} inserted, {} updated, {} deleted, {} unchanged",
+            self.inserted, self.updated, self.deleted, self.unchanged
+        )?;
+        if !self.failed.is_empty() {
+            write!(f, ", {} failed", self.failed.len())?;
+        }
+        Ok(())
+    }
+}
+
+// prune 只在 upsert 模式下有意義: 寫入後刪除資料集中沒有的文件
+pub async fn import(
+    collection: &Collection<KanaMapping>,
+    mappings: Vec<KanaMapping>,
+    options: &ImportOptions,
+) -> mongodb::error::Result<ImportSummary> {
+    match options.mode {
+        ImportMode::Insert => insert(collection, mappings, options).await,
+        ImportMode::Upsert => upsert(collection, &mappings, options).await,
+    }
+}
+
+pub async fn insert(
+    collection: &Collection<KanaMapping>,
+    mappings: Vec<KanaMapping>,
+    options: &ImportOptions,
+) -> mongodb::error::Result<ImportSummary> {
+    let mut summary = ImportSummary::default();
+    insert_batches(collection, &mappings, options, &mut summary).await?;
+    Ok(summary)
+}
+
+// 依 batch_size 分批送出 update 指令, 每份文件以 (kana_type, kana) upsert; 指令冪等, 因此整批重試是安全的
+pub async fn upsert(
+    collection: &Collection<KanaMapping>,
+    mappings: &[KanaMapping],
+    options: &ImportOptions,
+) -> mongodb::error::Result<ImportSummary> {
+    let mut summary = ImportSummary::default();
+    for batch in mappings.chunks(options.batch_size.max(1)) {
+        let result = options.retry.run("update", |_| upsert_batch(collection, batch, options.ordered, None)).await?;
+        summary.add(result);
+        if summary.stopped(options) {
+            return Ok(summary);
+        }
+    }
+    if options.prune {
+        let ids = stale_ids(plan::read_existing(collection).await?, mappings);
+        for batch in ids.chunks(options.batch_size.max(1)) {
+            let filter = doc! { "_id": { "$in": batch } };
+            summary.deleted +=
+                options.retry.run("delete_many", |_| collection.delete_many(filter.clone(), None)).await?.deleted_count;
+        }
+    }
+    Ok(summary)
+}
+
+// 只 $set 資料集中的欄位並 $unset 未設定的選用欄位, 其他應用程式加入的欄位保持不變
+fn upsert_statement(mapping: &KanaMapping) -> mongodb::error::Result<Document> {
+    let set = bson::to_document(mapping)?;
+    let unset: Document = OPTIONAL_FIELDS
+        .into_iter()
+        .filter(|field| !set.contains_key(field))
+        .map(|field| (field.to_string(), Bson::String(String::new())))
+        .collect();
+    let mut update = doc! { "$set": set };
+    if !unset.is_empty() {
+        update.insert("$unset", unset);
+    }
+    Ok(doc! {
+        "q": { "kana_type": mapping.kana_type.as_str(), "kana": &mapping.kana },
+        "u": update,
+        "upsert": true,
+    })
+}
+
+// update 指令的回應; writeErrors 為個別文件的錯誤, 指令本身仍視為成功
+#[derive(Debug, Deserialize)]
+struct UpdateResponse {
+    n: u64,
+    #[serde(rename = "nModified", default)]
+    modified: u64,
+    #[serde(default)]
+    upserted: Vec<Document>,
+    #[serde(rename = "writeErrors", default)]
+    write_errors: Vec<CommandWriteError>,
+    #[serde(rename = "writeConcernError")]
+    write_concern_error: Option<WriteConcernError>,
+}
+
+#[derive(Debug, Deserialize)]
+struct CommandWriteError {
+    index: usize,
+    code: i32,
+    #[serde(default)]
+    errmsg: String,
+}
+
+// session 為 None 時套用集合的 write concern; transaction 中的指令不可指定 write concern
+async fn upsert_batch(
+    collection: &Collection<KanaMapping>,
+    batch: &[KanaMapping],
+    ordered: bool,
+    session: Option<&mut ClientSession>,
+) -> mongodb::error::Result<ImportSummary> {
+    let updates = batch.iter().map(upsert_statement).collect::<mongodb::error::Result<Vec<_>>>()?;
+    let mut command = doc! { "update": collection.name(), "updates": updates, "ordered": ordered };
+    let db = collection.client().database(&collection.namespace().db);
+    let reply = match session {
+        Some(session) => db.run_command_with_session(command, None, session).await?,
+        None => {
+            if let Some(write_concern) = collection.write_concern() {
+                command.insert("writeConcern", bson::to_bson(write_concern)?);
+            }
+            db.run_command(command, None).await?
+        }
+    };
+
+    bson::from_document::<UpdateResponse>(reply)?.summary(batch)
+}
+
+impl UpdateResponse {
+    // upserted 為新增的文件, nModified 為內容有變更的文件, 其餘比對到的文件視為未變更
+    fn summary(self, batch: &[KanaMapping]) -> mongodb::error::Result<ImportSummary> {
+        if let Some(error) = self.write_concern_error {
+            return Err(ErrorKind::Write(WriteFailure::WriteConcernError(error)).into());
+        }
+        let inserted = self.upserted.len() as u64;
+        Ok(ImportSummary {
+            inserted,
+            updated: self.modified,
+            unchanged: self.n.saturating_sub(inserted + self.modified),
+            failed: self
+                .write_errors
+                .iter()
+                .map(|error| DocumentError::new(&batch[error.index], error.code, &error.errmsg))
+                .collect(),
+            ..Default::default()
+        })
+    }
+}
+
+// 集合中不在資料集裡的文件, 以及同一個鍵多出來的重複文件
+fn stale_ids(existing: Vec<plan::Existing>, mappings: &[KanaMapping]) -> Vec<Bson> {
+    plan::compute(existing, mappings, true).deletes.into_iter().map(|existing| existing.id).collect()
+}
+
+// 依 batch_size 分批 insert_many
+async fn insert_batches(
+    collection: &Collection<KanaMapping>,
+    mappings: &[KanaMapping],
+    options: &ImportOptions,
+    summary: &mut ImportSummary,
+) -> mongodb::error::Result<()> {
+    for batch in mappings.chunks(options.batch_size.max(1)) {
+        let (inserted, failed) = insert_batch(collection, batch, options).await?;
+        summary.inserted += inserted;
+        summary.failed.extend(failed);
+        if summary.stopped(options) {
+            break;
+        }
+    }
+    Ok(())
+}
+
+// 回傳寫入的文件數與個別文件的錯誤
+// 中斷前可能已寫入部分文件: _id 在第一次嘗試前就指定好, 重試時改以 unordered 插入並忽略 _id 重複的錯誤
+async fn insert_batch(
+    collection: &Collection<KanaMapping>,
+    batch: &[KanaMapping],
+    options: &ImportOptions,
+) -> mongodb::error::Result<(u64, Vec<DocumentError>)> {
+    let documents = &with_ids(batch)?;
+    let collection = &collection.clone_with_type::<Document>();
+    options
+        .retry
+        .run("insert_many", |attempt| async move {
+            let retrying = attempt > 0;
+            let ordered = options.ordered && !retrying;
+            let insert_options = InsertManyOptions::builder().ordered(ordered).build();
+            let errors = match collection.insert_many(documents, insert_options).await {
+                Ok(_) => return Ok((batch.len() as u64, Vec::new())),
+                Err(e) => match bulk_write_errors(&e) {
+                    Some(errors) => errors.to_vec(),
+                    None => return Err(e),
+                },
+            };
+            // 與 (kana_type, kana) 唯一索引衝突的仍是錯誤
+            let failed: Vec<DocumentError> = errors
+                .iter()
+                .filter(|error| !(retrying && is_duplicate_id(error.code, &error.message)))
+                .map(|error| DocumentError::new(&batch[error.index], error.code, &error.message))
+                .collect();
+            // ordered 時只有第一個錯誤之前的文件已寫入
+            let inserted = match errors.first() {
+                Some(first) if ordered => first.index,
+                _ => batch.len() - failed.len(),
+            };
+            Ok((inserted as u64, failed))
+        })
+        .await
+}
+
+// 由 client 端指定 _id, 每次重試都送出相同的文件
+fn with_ids(batch: &[KanaMapping]) -> mongodb::error::Result<Vec<Document>> {
+    batch
+        .iter()
+        .map(|mapping| {
+            let mut document = bson::to_document(mapping)?;
+            document.insert("_id", ObjectId::new());
+            Ok(document)
+        })
+        .collect()
+}
+
+// 前一次嘗試已寫入的文件: 錯誤訊息為 "E11000 duplicate key error collection: db.coll index: _id_ dup key: ..."
+fn is_duplicate_id(code: i32, message: &str) -> bool {
+    code == DUPLICATE_KEY && message.contains("index: _id_ ")
+}
+
+// 在 transaction 中完成整個匯入, 讀取者只會看到匯入前或匯入後的資料
+// 任一文件寫入失敗就放棄整個 transaction; TransientTransactionError 時依 retry 重新執行整個 transaction
+pub async fn import_in_transaction(
+    collection: &Collection<KanaMapping>,
+    mappings: &[KanaMapping],
+    options: &ImportOptions,
+) -> mongodb::error::Result<ImportSummary> {
+    options
+        .retry
+        .run("transaction", |_| async move {
+            let mut session = collection.client().start_session(None).await?;
+            // transaction 中的讀取只能送到 primary, 不沿用 --read-preference
+            let transaction_options = TransactionOptions::builder()
+                .selection_criteria(SelectionCriteria::ReadPreference(ReadPreference::Primary))
+                .build();
+            session.start_transaction(transaction_options).await?;
+            match write_in_session(collection, mappings, options, &mut session).await {
+                Ok(summary) if summary.failed.is_empty() => {
+                    commit(&mut session, &options.retry).await?;
+                    Ok(summary)
+                }
+                // 放棄的 transaction 不會留下任何寫入
+                Ok(summary) => {
+                    session.abort_transaction().await?;
+                    Ok(ImportSummary { failed: summary.failed, ..Default::default() })
+                }
+                Err(e) => {
+                    // 以原本的錯誤為準, 放棄失敗時伺服器也會在逾時後自動放棄
+                    let _ = session.abort_transaction().await;
+                    Err(e)
+                }
+            }
+        })
+        .await
+}
+
+// transaction 中的第一個錯誤就會讓伺服器放棄 transaction, 因此一律在失敗時停止
+async fn write_in_session(
+    collection: &Collection<KanaMapping>,
+    mappings: &[KanaMapping],
+    options: &ImportOptions,
+    session: &mut ClientSession,
+) -> mongodb::error::Result<ImportSummary> {
+    let mut summary = ImportSummary::default();
+    for batch in mappings.chunks(options.batch_size.max(1)) {
+        match options.mode {
+            ImportMode::Insert => match collection.insert_many_with_session(batch, None, &mut *session).await {
+                Ok(_) => summary.inserted += batch.len() as u64,
+                Err(e) => match bulk_write_errors(&e) {
+                    Some(errors) => {
+                        let failed = errors.iter().map(|error| DocumentError::new(&batch[error.index], error.code, &error.message));
+                        summary.failed.extend(failed);
+                    }
+                    None => return Err(e),
+                },
+            },
+            ImportMode::Upsert => summary.add(upsert_batch(collection, batch, true, Some(&mut *session)).await?),
+        }
+        if !summary.failed.is_empty() {
+            return Ok(summary);
+        }
+    }
+    if options.mode == ImportMode::Upsert && options.prune {
+        let ids = stale_ids(plan::read_existing_with_session(collection, session).await?, mappings);
+        for batch in ids.chunks(options.batch_size.max(1)) {
+            let filter = doc! { "_id": { "$in": batch } };
+            summary.deleted += collection.delete_many_with_session(filter, None, session).await?.deleted_count;
+        }
+    }
+    Ok(summary)
+}
+
+// UnknownTransactionCommitResult 時重試 commit, 重複 commit 同一個 transaction 是安全的
+async fn commit(session: &mut ClientSession, retry: &RetryPolicy) -> mongodb::error::Result<()> {
+    let mut attempt = 0;
+    loop {
+        match session.commit_transaction().await {
+            Err(e) if attempt < retry.max_retries && e.contains_label(UNKNOWN_TRANSACTION_COMMIT_RESULT) => {
+                let delay = retry.delay(attempt);
+                attempt += 1;
+                eprintln!(
+                    "Warning: commitTransaction failed, retrying in {} ms ({}/{}): {}",
+                    delay.as_millis(),
+                    attempt,
+                    retry.max_retries,
+                    e
+                );
+                tokio::time::sleep(delay).await;
+            }
+            result => return result,
+        }
+    }
+}
+
+// insert_many 中個別文件的錯誤; 網路、write concern 等其他錯誤為 None
+fn bulk_write_errors(error: &mongodb::error::Error) -> Option<&[BulkWriteError]> {
+    match &*error.kind {
+        ErrorKind::BulkWrite(BulkWriteFailure { write_errors: Some(errors), write_concern_error: None, .. }) => {
+            Some(errors)
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::{Category, Usage};
+
+    fn extended(kana: &str, romaji: &str) -> KanaMapping {
+        KanaMapping::new(KanaType::Katakana, Category::ExtendedKatakana, kana, romaji)
+    }
+
+    #[test]
+    fn insert_documents_get_distinct_ids_before_the_first_attempt() {
+        let documents = with_ids(&[extended("ティ", "ti"), extended("ディ", "di")]).unwrap();
+        let ids: Vec<ObjectId> = documents.iter().map(|document| document.get_object_id("_id").unwrap()).collect();
+        assert_ne!(ids[0], ids[1]);
+        assert_eq!(documents[0].get_str("kana"), Ok("ティ"));
+    }
+
+    #[test]
+    fn retry_ignores_only_duplicates_of_the_id_index() {
+        let message = |index: &str| {
+            format!("E11000 duplicate key error collection: jp.kana_mappings index: {} dup key: {{ ... }}", index)
+        };
+        assert!(is_duplicate_id(DUPLICATE_KEY, &message("_id_")));
+        assert!(!is_duplicate_id(DUPLICATE_KEY, &message("kana_type_1_kana_1")));
+        assert!(!is_duplicate_id(121, &message("_id_")));
+    }
+
+    #[test]
+    fn upsert_statement_sets_dataset_fields_and_unsets_empty_optional_fields() {
+        let mapping = KanaMapping { usage: Some(Usage::Standard), ..extended("ティ", "ti") };
+        let statement = upsert_statement(&mapping).unwrap();
+        assert_eq!(statement.get_document("q").unwrap(), &doc! { "kana_type": "katakana", "kana": "ティ" });
+        assert_eq!(statement.get_bool("upsert"), Ok(true));
+
+        let update = statement.get_document("u").unwrap();
+        let set = update.get_document("$set").unwrap();
+        assert_eq!(set.get_str("romaji"), Ok("ti"));
+        assert_eq!(set.get_str("usage"), Ok("standard"));
+        let unset: Vec<&String> = update.get_document("$unset").unwrap().keys().collect();
+        assert_eq!(unset, ["role", "fullwidth", "modern_kana", "beginner"]);
+    }
+
+    #[test]
+    fn update_response_counts_upserted_modified_and_unchanged() {
+        let batch = [extended("ティ", "ti"), extended("ファ", "fa"), extended("ヴァ", "va"), extended("ヴ", "vu")];
+        let reply = doc! {
+            "n": 3,
+            "nModified": 1,
+            "upserted": [{ "index": 0, "_id": 1 }],
+            "writeErrors": [{ "index": 3, "code": 121, "errmsg": "Document failed validation" }],
+            "ok": 1,
+        };
+        let summary = bson::from_document::<UpdateResponse>(reply).unwrap().summary(&batch).unwrap();
+        assert_eq!((summary.inserted, summary.updated, summary.unchanged), (1, 1, 1));
+        assert_eq!(summary.failed, [DocumentError::new(&batch[3], 121, "Document failed validation")]);
+    }
+
+    #[test]
+    fn update_response_with_write_concern_error_fails() {
+        let reply = doc! { "n": 1, "nModified": 1, "writeConcernError": { "code": 64, "errmsg": "waiting for replication timed out" } };
+        let error = bson::from_document::<UpdateResponse>(reply).unwrap().summary(&[extended("ティ", "ti")]).unwrap_err();
+        assert!(matches!(*error.kind, ErrorKind::Write(WriteFailure::WriteConcernError(_))));
+    }
+}
@@ -0,0 +1,174 @@
+use crate::model::KanaMapping;
+use crate::retry::RetryPolicy;
+use futures::TryStreamExt;
+use mongodb::{
+    bson::{doc, Bson, Document},
+    error::ErrorKind,
+    options::{AggregateOptions, IndexOptions},
+    Collection, IndexModel,
+};
+use std::fmt;
+
+// NamespaceNotFound: 集合尚未建立
+const NAMESPACE_NOT_FOUND: i32 = 26;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum IndexStatus {
+    Created,
+    Exists,
+    // 已有同名或同欄位的索引, 但定義與預期不同, 不會自動修改
+    Conflict(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct IndexReport {
+    pub name: String,
+    pub status: IndexStatus,
+}
+
+impl fmt::Display for IndexReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.status {
+            IndexStatus::Created => write!(f, "index `{}` created", self.name),
+            IndexStatus::Exists => write!(f, "index `{}` already exists", self.name),
+            IndexStatus::Conflict(reason) => write!(f, "index `{}` conflicts with an existing index: {}", self.name, reason),
+        }
+    }
+}
+
+// kana_mappings 需要的索引: 自然鍵的唯一索引, 以及 romaji / category / fullwidth 的查詢索引
+pub fn desired_indexes() -> Vec<IndexModel> {
+    let index = |name: &str, keys: Document, unique: bool| {
+        IndexModel::builder()
+            .keys(keys)
+            .options(IndexOptions::builder().name(name.to_string()).unique(unique.then_some(true)).build())
+            .build()
+    };
+    vec![
+        index("kana_type_1_kana_1", doc! { "kana_type": 1, "kana": 1 }, true),
+        index("romaji_1", doc! { "romaji": 1 }, false),
+        index("category_1", doc! { "category": 1 }, false),
+        index("fullwidth_1", doc! { "fullwidth": 1 }, false),
+    ]
+}
+
+pub async fn ensure_indexes(collection: &Collection<KanaMapping>) -> mongodb::error::Result<Vec<IndexReport>> {
+    let existing = existing_indexes(collection).await?;
+
+    let mut reports = Vec::new();
+    let mut missing = Vec::new();
+    for desired in desired_indexes() {
+        let name = index_name(&desired);
+        let status = match existing.iter().find(|index| index_name(index) == name || same_keys(&index.keys, &desired.keys)) {
+            Some(index) => match conflict(index, &desired) {
+                Some(reason) => IndexStatus::Conflict(reason),
+                None => IndexStatus::Exists,
+            },
+            None => {
+                missing.push(desired);
+                IndexStatus::Created
+            }
+        };
+        reports.push(IndexReport { name, status });
+    }
+
+    if !missing.is_empty() {
+        collection.create_indexes(missing, None).await?;
+    }
+    Ok(reports)
+}
+
+// 唯一索引無法建立在含有重複鍵的集合上; 每個 (kana_type, kana) 保留 _id 最小的一份, 刪除其餘文件
+pub async fn remove_duplicates(
+    collection: &Collection<KanaMapping>,
+    retry: &RetryPolicy,
+    batch_size: usize,
+) -> mongodb::error::Result<u64> {
+    let pipeline = [
+        doc! { "$sort": { "_id": 1 } },
+        doc! { "$group": { "_id": { "kana_type": "$kana_type", "kana": "$kana" }, "ids": { "$push": "$_id" } } },
+        doc! { "$match": { "ids.1": { "$exists": true } } },
+    ];
+    let options = AggregateOptions::builder().allow_disk_use(true).build();
+    let groups: Vec<Document> = collection.aggregate(pipeline, options).await?.try_collect().await?;
+
+    let mut deleted = 0;
+    for batch in duplicate_ids(groups).chunks(batch_size.max(1)) {
+        let filter = doc! { "_id": { "$in": batch } };
+        deleted += retry.run("delete_many", |_| collection.delete_many(filter.clone(), None)).await?.deleted_count;
+    }
+    Ok(deleted)
+}
+
+// 每組 ids 依 _id 排序, 第一份之後的都是重複的文件
+fn duplicate_ids(groups: Vec<Document>) -> Vec<Bson> {
+    groups
+        .into_iter()
+        .filter_map(|mut group| match group.remove("ids") {
+            Some(Bson::Array(ids)) => Some(ids.into_iter().skip(1)),
+            _ => None,
+        })
+        .flatten()
+        .collect()
+}
+
+async fn existing_indexes(collection: &Collection<KanaMapping>) -> mongodb::error::Result<Vec<IndexModel>> {
+    match collection.list_indexes(None).await {
+        Ok(cursor) => cursor.try_collect().await,
+        Err(e) if matches!(*e.kind, ErrorKind::Command(ref command) if command.code == NAMESPACE_NOT_FOUND) => Ok(Vec::new()),
+        Err(e) => Err(e),
+    }
+}
+
+fn index_name(index: &IndexModel) -> String {
+    index.options.as_ref().and_then(|options| options.name.clone()).unwrap_or_default()
+}
+
+fn is_unique(index: &IndexModel) -> bool {
+    index.options.as_ref().and_then(|options| options.unique).unwrap_or(false)
+}
+
+fn conflict(existing: &IndexModel, desired: &IndexModel) -> Option<String> {
+    if index_name(existing) != index_name(desired) {
+        return Some(format!("the same keys are indexed as `{}`", index_name(existing)));
+    }
+    if !same_keys(&existing.keys, &desired.keys) {
+        return Some(format!("existing keys are {}, expected {}", existing.keys, desired.keys));
+    }
+    if is_unique(existing) != is_unique(desired) {
+        return Some(format!("existing index unique: {}, expected unique: {}", is_unique(existing), is_unique(desired)));
+    }
+    None
+}
+
+// 伺服器回傳的方向可能是 int32, int64 或 double, 依數值比較
+fn same_keys(a: &Document, b: &Document) -> bool {
+    let direction = |value: &Bson| match value {
+        Bson::Int32(n) => Some(*n as f64),
+        Bson::Int64(n) => Some(*n as f64),
+        Bson::Double(n) => Some(*n),
+        _ => None,
+    };
+    a.len() == b.len()
+        && a.iter().zip(b.iter()).all(|((key_a, value_a), (key_b, value_b))| {
+            key_a == key_b
+                && match (direction(value_a), direction(value_b)) {
+                    (Some(x), Some(y)) => x == y,
+                    _ => value_a == value_b,
+                }
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn duplicate_ids_keep_the_first_document_of_each_key() {
+        let groups = vec![
+            doc! { "_id": { "kana_type": "hiragana", "kana": "あ" }, "ids": [1, 2, 3] },
+            doc! { "_id": { "kana_type": "katakana", "kana": "ア" }, "ids": [4, 5] },
+        ];
+        assert_eq!(duplicate_ids(groups), [Bson::Int32(2), Bson::Int32(3), Bson::Int32(5)]);
+    }
+}
@@ -0,0 +1,22 @@
+//! 日文假名資料的模型、內建資料集, 以及匯入 MongoDB 所需的函式
+
+pub mod config;
+pub mod connection;
+pub mod dataset;
+pub mod error;
+pub mod health;
+pub mod import;
+pub mod index;
+pub mod model;
+pub mod parse;
+pub mod plan;
+pub mod retry;
+pub mod stats;
+pub mod validator;
+
+pub use error::{Error, Result};
+pub use model::{Category, KanaMapping, KanaType};
+
+// 預設的資料庫與集合名稱
+pub const DEFAULT_DATABASE: &str = "jp_syllabaries";
+pub const DEFAULT_COLLECTION: &str = "kana_mappings";
@@ -1,254 +1,498 @@
+use clap::{Args, Parser, Subcommand};
+use dotenv::dotenv;
+use kana_data_importer::{
+    config::{self, Profile, Settings},
+    connection::{self, ConnectionConfig, StableApi},
+    dataset::{self, Dataset, Format},
+    error::ConfigError,
+    health::{self, Topology},
+    import::{self, ImportMode, ImportOptions},
+    index,
+    plan::{self, OutputFormat, Plan},
+    retry::RetryPolicy,
+    stats, validator, Error, KanaMapping, Result,
+};
 use mongodb::{
-    bson::doc,
-    options::{ClientOptions, ServerApi, ServerApiVersion},
-    Client, Collection,
+    error::ErrorKind,
+    options::{Acknowledgment, AuthMechanism, ReadPreference, ValidationLevel},
+    Collection, Database,
 };
-use serde::{Deserialize, Serialize};
-use tokio;
-use dotenv::dotenv;
-use std::env;
+use std::io::{self, IsTerminal};
+use std::path::PathBuf;
+use std::process;
+
+#[derive(Parser)]
+#[command(
+    version,
+    about = "Import japanese syllabaries data to MongoDB",
+    after_help = "Exit codes: 0 success, 2 invalid arguments, 3 connection failure, 4 validation failure, \
+                  5 invalid dataset, 6 configuration error, 7 write failure, 8 insufficient privileges"
+)]
+struct Cli {
+    /// Config file with [profiles.<name>] sections [env: KANA_CONFIG] [default: kana_importer.toml if present]
+    #[arg(long, global = true, value_name = "PATH")]
+    config: Option<PathBuf>,
+    /// Profile of the config file to use [env: KANA_PROFILE]
+    #[arg(long, global = true)]
+    profile: Option<String>,
+    /// Database that holds the kana mappings [env: MONGO_DATABASE] [default: jp_syllabaries]
+    #[arg(long, global = true)]
+    database: Option<String>,
+    /// Collection that holds the kana mappings [env: MONGO_COLLECTION] [default: kana_mappings]
+    #[arg(long, global = true)]
+    collection: Option<String>,
+    /// Number of documents per write batch [env: KANA_BATCH_SIZE] [default: 1000]
+    #[arg(long, global = true, value_parser = config::parse_batch_size)]
+    batch_size: Option<usize>,
+    /// Retries for transient connection and write errors, with jittered exponential backoff [env: KANA_MAX_RETRIES] [default: 3]
+    #[arg(long, global = true, value_parser = config::parse_max_retries)]
+    max_retries: Option<u32>,
+    /// Full connection string, e.g. mongodb://localhost:27017 [env: MONGO_URI]
+    #[arg(long, global = true)]
+    uri: Option<String>,
+    /// Authentication mechanism, e.g. SCRAM-SHA-256 [env: MONGO_AUTH_MECHANISM]
+    #[arg(long, global = true, value_parser = connection::parse_auth_mechanism)]
+    auth_mechanism: Option<AuthMechanism>,
+    /// Prompt for the password without echoing it, or read it from the first line of stdin when piped
+    #[arg(long, global = true)]
+    ask_password: bool,
+    /// Database that holds the user's credentials [env: MONGO_AUTH_SOURCE]
+    #[arg(long, global = true)]
+    auth_source: Option<String>,
+    /// Application name reported to the server [env: MONGO_APP_NAME]
+    #[arg(long, global = true)]
+    app_name: Option<String>,
+    /// Write concern: majority, a number of nodes (at least 1) or a tag set name [env: MONGO_WRITE_CONCERN]
+    #[arg(long, global = true, value_parser = connection::parse_write_concern)]
+    write_concern: Option<Acknowledgment>,
+    /// Read preference: primary, primaryPreferred, secondary, secondaryPreferred or nearest [env: MONGO_READ_PREFERENCE]
+    #[arg(long, global = true, value_parser = connection::parse_read_preference)]
+    read_preference: Option<ReadPreference>,
+    /// PEM file of the CA that signed the server certificate [env: MONGO_TLS_CA_FILE]
+    #[arg(long, global = true, value_name = "PATH")]
+    tls_ca_file: Option<PathBuf>,
+    /// PEM file with the client certificate and its private key [env: MONGO_TLS_CERTIFICATE_KEY_FILE]
+    #[arg(long, global = true, value_name = "PATH")]
+    tls_certificate_key_file: Option<PathBuf>,
+    /// Accept server certificates whose hostname does not match, for development only [env: MONGO_TLS_ALLOW_INVALID_HOSTNAMES]
+    #[arg(long, global = true)]
+    tls_allow_invalid_hostnames: bool,
+    /// Stable API version to declare, 1 or none for servers without Stable API support [env: MONGO_SERVER_API]
+    #[arg(long, global = true)]
+    server_api: Option<StableApi>,
+    /// Reject commands that are not part of the Stable API [env: MONGO_SERVER_API_STRICT]
+    #[arg(long, global = true)]
+    server_api_strict: bool,
+    /// Fail on functionality deprecated in the Stable API [env: MONGO_SERVER_API_DEPRECATION_ERRORS]
+    #[arg(long, global = true)]
+    server_api_deprecation_errors: bool,
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Import kana mappings into MongoDB (the default command)
+    Import(ImportArgs),
+    /// Export the collection to a dataset file
+    Export(ExportArgs),
+    /// Check that the collection matches the dataset
+    Verify(DatasetArgs),
+    /// Show what an import would change without writing anything
+    #[command(alias = "plan")]
+    Diff(DiffArgs),
+    /// Count documents by kana_type and category
+    Stats,
+    /// Check the connection, topology, server version and privileges on the target collection
+    Check,
+    /// Drop the collection
+    Drop(DropArgs),
+    /// Inspect the effective configuration
+    #[command(subcommand)]
+    Config(ConfigCommand),
+}
+
+#[derive(Subcommand)]
+enum ConfigCommand {
+    /// Print the effective configuration with secrets redacted
+    Show,
+}
+
+#[derive(Args)]
+struct DatasetArgs {
+    /// Dataset file to use instead of the builtin data/kana.csv [env: KANA_DATASET]
+    #[arg(long, value_name = "PATH")]
+    from: Option<PathBuf>,
+    /// Dataset format (csv, tsv, json, ndjson, yaml, toml), detected from the extension by default
+    #[arg(long, requires = "from")]
+    format: Option<Format>,
+}
+
+#[derive(Parser)]
+struct ImportArgs {
+    #[command(flatten)]
+    dataset: DatasetArgs,
+    /// `upsert` writes only the differences keyed on (kana_type, kana), `insert` appends blindly
+    #[arg(long, default_value = "upsert")]
+    mode: ImportMode,
+    /// Delete documents that are not in the dataset (upsert mode only)
+    #[arg(long)]
+    prune: bool,
+    /// Keep inserting the remaining documents of a batch after a document fails (ignored inside a transaction)
+    #[arg(long)]
+    unordered: bool,
+    /// Write without a transaction even on a replica set or sharded cluster
+    #[arg(long)]
+    no_transaction: bool,
+    /// Import a dataset that lacks some cells of the standard youon table, e.g. a partial word list
+    #[arg(long)]
+    allow_incomplete: bool,
+    /// Create or update the collection's $jsonSchema validator, needs the collMod action (e.g. the dbAdmin role)
+    #[arg(long)]
+    install_validator: bool,
+    /// Validation level of the installed validator (off, moderate, strict)
+    #[arg(long, default_value = "strict", value_parser = validator::parse_validation_level, requires = "install_validator")]
+    validation_level: ValidationLevel,
+    /// Print the plan instead of writing to the collection
+    #[arg(long)]
+    dry_run: bool,
+    /// Output format of the dry-run plan (text, json)
+    #[arg(long, default_value = "text")]
+    output: OutputFormat,
+}
+
+#[derive(Args)]
+struct ExportArgs {
+    /// File to write, stdout by default
+    #[arg(long, value_name = "PATH")]
+    to: Option<PathBuf>,
+    /// Dataset format (csv, tsv, json, ndjson, yaml, toml), detected from the extension or json for stdout
+    #[arg(long)]
+    format: Option<Format>,
+}
 
-#[derive(Debug, Serialize, Deserialize)]
-struct KanaMapping {
-    kana_type: String,
-    category: String,
-    kana: String,
-    romaji: String,
+#[derive(Args)]
+struct DiffArgs {
+    #[command(flatten)]
+    dataset: DatasetArgs,
+    /// Also list documents that are not in the dataset as deletions
+    #[arg(long)]
+    prune: bool,
+    /// Output format (text, json)
+    #[arg(long, default_value = "text")]
+    output: OutputFormat,
+}
+
+#[derive(Args)]
+struct DropArgs {
+    /// Confirm dropping the collection
+    #[arg(long)]
+    yes: bool,
 }
 
 #[tokio::main]
-async fn main() -> mongodb::error::Result<()> {
-    // 加載 .env 文件中的環境變數
+async fn main() {
+    let cli = Cli::parse();
+    if let Err(e) = run(cli).await {
+        eprintln!("Error: {}", e);
+        process::exit(e.exit_code());
+    }
+}
+
+async fn run(cli: Cli) -> Result<()> {
+    // 加載 .env 文件中的環境變數; 優先順序為命令列參數 > 環境變數 > 設定檔的 profile > 預設值
     dotenv().ok();
+    let selected = config::select(cli.config, cli.profile)?;
+    let password = if cli.ask_password { Some(read_password()?) } else { None };
+    let cli_layer = Profile {
+        uri: cli.uri,
+        password,
+        auth_mechanism: cli.auth_mechanism,
+        auth_source: cli.auth_source,
+        app_name: cli.app_name,
+        write_concern: cli.write_concern,
+        read_preference: cli.read_preference,
+        tls_ca_file: cli.tls_ca_file,
+        tls_certificate_key_file: cli.tls_certificate_key_file,
+        tls_allow_invalid_hostnames: cli.tls_allow_invalid_hostnames.then_some(true),
+        server_api: cli.server_api,
+        server_api_strict: cli.server_api_strict.then_some(true),
+        server_api_deprecation_errors: cli.server_api_deprecation_errors.then_some(true),
+        database: cli.database,
+        collection: cli.collection,
+        batch_size: cli.batch_size,
+        max_retries: cli.max_retries,
+        ..Default::default()
+    };
+    let settings = cli_layer.or(Profile::from_env()?).or(selected.profile).resolve();
+
+    let target = Target::from(settings.clone());
+
+    // 未指定子命令時, 以預設選項匯入內建資料集
+    match cli.command.unwrap_or_else(|| Command::Import(ImportArgs::parse_from(["import"]))) {
+        Command::Import(args) => run_import(&target, args).await,
+        Command::Export(args) => run_export(&target, args).await,
+        Command::Verify(args) => run_verify(&target, args).await,
+        Command::Diff(args) => {
+            let kana_mappings = load_dataset(&target, &args.dataset)?.mappings();
+            run_diff(&target, kana_mappings, ImportMode::Upsert, args.prune, args.output).await
+        }
+        Command::Stats => run_stats(&target).await,
+        Command::Check => run_check(&target).await,
+        Command::Drop(args) => run_drop(&target, args).await,
+        Command::Config(ConfigCommand::Show) => {
+            println!("# config file: {}", selected.file.map_or("(none)".to_string(), |path| path.display().to_string()));
+            println!("# profile: {}", selected.name.as_deref().unwrap_or("(none)"));
+            println!("{}", settings);
+            Ok(())
+        }
+    }
+}
+
+struct Target {
+    connection: ConnectionConfig,
+    database: String,
+    collection: String,
+    batch_size: usize,
+    retry: RetryPolicy,
+    dataset: Option<PathBuf>,
+}
+
+impl From<Settings> for Target {
+    fn from(settings: Settings) -> Self {
+        Target {
+            connection: settings.connection,
+            database: settings.database,
+            collection: settings.collection,
+            batch_size: settings.batch_size,
+            retry: settings.retry,
+            dataset: settings.dataset,
+        }
+    }
+}
+
+impl Target {
+    async fn open(&self) -> Result<(Database, Collection<KanaMapping>)> {
+        let client = connection::connect(&self.connection, &self.retry).await?;
+        // 訊息寫到 stderr 以免混入 JSON 輸出
+        eprintln!("Pinged your deployment. You successfully connected to MongoDB!");
+        eprintln!("Connected to {}.", connection::server_info(&client).await);
+
+        let db = client.database(&self.database);
+        let collection = db.collection(&self.collection);
+        Ok((db, collection))
+    }
+}
+
+async fn run_import(target: &Target, args: ImportArgs) -> Result<()> {
+    // 先讀取資料集, 格式有誤時不必連線; --dry-run 也做相同的檢查
+    let dataset = load_dataset(target, &args.dataset)?;
+    let mappings = dataset.mappings();
+    if !args.allow_incomplete {
+        dataset::check_youon(&mappings, &dataset.source)?;
+    }
+    if args.dry_run {
+        return run_diff(target, mappings, args.mode, args.prune, args.output).await;
+    }
+    println!("Loaded {} kana mappings.", mappings.len());
+
+    let (db, collection) = target.open().await?;
 
-    // 從環境變數中讀取 MongoDB 連接資訊
-    let username = env::var("MONGO_USERNAME").expect("MONGO_USERNAME must be set");
-    let password = env::var("MONGO_PASSWORD").expect("MONGO_PASSWORD must be set");
-    let cluster = env::var("MONGO_CLUSTER").expect("MONGO_CLUSTER must be set");
-
-    let client_uri = format!(
-        "mongodb+srv://{}:{}@{}/?retryWrites=true&w=majority&appName=Cluster0",
-        username, password, cluster
-    );
-    let mut client_options = ClientOptions::parse(&client_uri).await?;
-
-    // 設定 Stable API 版本
-    let server_api = ServerApi::builder().version(ServerApiVersion::V1).build();
-    client_options.server_api = Some(server_api);
-
-    // 取得 cluster 的 handle
-    let client = Client::with_options(client_options)?;
-
-    // Ping server 以確認連接成功
-    client.database("admin").run_command(doc! {"ping": 1}, None).await?;
-    println!("Pinged your deployment. You successfully connected to MongoDB!");
-
-    // 選擇資料庫和集合
-    let db = client.database("jp_syllabaries");
-    let collection: Collection<KanaMapping> = db.collection("kana_mappings");
-
-    // 準備批次插入的資料
-    let kana_mappings = vec![
-        // 平假名清音 (Hiragana Seion)
-        KanaMapping { kana_type: "hiragana".to_string(), category: "seion".to_string(), kana: "あ".to_string(), romaji: "a".to_string() },
-        KanaMapping { kana_type: "hiragana".to_string(), category: "seion".to_string(), kana: "い".to_string(), romaji: "i".to_string() },
-        KanaMapping { kana_type: "hiragana".to_string(), category: "seion".to_string(), kana: "う".to_string(), romaji: "u".to_string() },
-        KanaMapping { kana_type: "hiragana".to_string(), category: "seion".to_string(), kana: "え".to_string(), romaji: "e".to_string() },
-        KanaMapping { kana_type: "hiragana".to_string(), category: "seion".to_string(), kana: "お".to_string(), romaji: "o".to_string() },
-        KanaMapping { kana_type: "hiragana".to_string(), category: "seion".to_string(), kana: "か".to_string(), romaji: "ka".to_string() },
-        KanaMapping { kana_type: "hiragana".to_string(), category: "seion".to_string(), kana: "き".to_string(), romaji: "ki".to_string() },
-        KanaMapping { kana_type: "hiragana".to_string(), category: "seion".to_string(), kana: "く".to_string(), romaji: "ku".to_string() },
-        KanaMapping { kana_type: "hiragana".to_string(), category: "seion".to_string(), kana: "け".to_string(), romaji: "ke".to_string() },
-        KanaMapping { kana_type: "hiragana".to_string(), category: "seion".to_string(), kana: "こ".to_string(), romaji: "ko".to_string() },
-        KanaMapping { kana_type: "hiragana".to_string(), category: "seion".to_string(), kana: "さ".to_string(), romaji: "sa".to_string() },
-        KanaMapping { kana_type: "hiragana".to_string(), category: "seion".to_string(), kana: "し".to_string(), romaji: "shi".to_string() },
-        KanaMapping { kana_type: "hiragana".to_string(), category: "seion".to_string(), kana: "す".to_string(), romaji: "su".to_string() },
-        KanaMapping { kana_type: "hiragana".to_string(), category: "seion".to_string(), kana: "せ".to_string(), romaji: "se".to_string() },
-        KanaMapping { kana_type: "hiragana".to_string(), category: "seion".to_string(), kana: "そ".to_string(), romaji: "so".to_string() },
-        KanaMapping { kana_type: "hiragana".to_string(), category: "seion".to_string(), kana: "た".to_string(), romaji: "ta".to_string() },
-        KanaMapping { kana_type: "hiragana".to_string(), category: "seion".to_string(), kana: "ち".to_string(), romaji: "chi".to_string() },
-        KanaMapping { kana_type: "hiragana".to_string(), category: "seion".to_string(), kana: "つ".to_string(), romaji: "tsu".to_string() },
-        KanaMapping { kana_type: "hiragana".to_string(), category: "seion".to_string(), kana: "て".to_string(), romaji: "te".to_string() },
-        KanaMapping { kana_type: "hiragana".to_string(), category: "seion".to_string(), kana: "と".to_string(), romaji: "to".to_string() },
-        KanaMapping { kana_type: "hiragana".to_string(), category: "seion".to_string(), kana: "な".to_string(), romaji: "na".to_string() },
-        KanaMapping { kana_type: "hiragana".to_string(), category: "seion".to_string(), kana: "に".to_string(), romaji: "ni".to_string() },
-        KanaMapping { kana_type: "hiragana".to_string(), category: "seion".to_string(), kana: "ぬ".to_string(), romaji: "nu".to_string() },
-        KanaMapping { kana_type: "hiragana".to_string(), category: "seion".to_string(), kana: "ね".to_string(), romaji: "ne".to_string() },
-        KanaMapping { kana_type: "hiragana".to_string(), category: "seion".to_string(), kana: "の".to_string(), romaji: "no".to_string() },
-        KanaMapping { kana_type: "hiragana".to_string(), category: "seion".to_string(), kana: "は".to_string(), romaji: "ha".to_string() },
-        KanaMapping { kana_type: "hiragana".to_string(), category: "seion".to_string(), kana: "ひ".to_string(), romaji: "hi".to_string() },
-        KanaMapping { kana_type: "hiragana".to_string(), category: "seion".to_string(), kana: "ふ".to_string(), romaji: "fu".to_string() },
-        KanaMapping { kana_type: "hiragana".to_string(), category: "seion".to_string(), kana: "へ".to_string(), romaji: "he".to_string() },
-        KanaMapping { kana_type: "hiragana".to_string(), category: "seion".to_string(), kana: "ほ".to_string(), romaji: "ho".to_string() },
-        KanaMapping { kana_type: "hiragana".to_string(), category: "seion".to_string(), kana: "ま".to_string(), romaji: "ma".to_string() },
-        KanaMapping { kana_type: "hiragana".to_string(), category: "seion".to_string(), kana: "み".to_string(), romaji: "mi".to_string() },
-        KanaMapping { kana_type: "hiragana".to_string(), category: "seion".to_string(), kana: "む".to_string(), romaji: "mu".to_string() },
-        KanaMapping { kana_type: "hiragana".to_string(), category: "seion".to_string(), kana: "め".to_string(), romaji: "me".to_string() },
-        KanaMapping { kana_type: "hiragana".to_string(), category: "seion".to_string(), kana: "も".to_string(), romaji: "mo".to_string() },
-        KanaMapping { kana_type: "hiragana".to_string(), category: "seion".to_string(), kana: "や".to_string(), romaji: "ya".to_string() },
-        KanaMapping { kana_type: "hiragana".to_string(), category: "seion".to_string(), kana: "ゆ".to_string(), romaji: "yu".to_string() },
-        KanaMapping { kana_type: "hiragana".to_string(), category: "seion".to_string(), kana: "よ".to_string(), romaji: "yo".to_string() },
-        KanaMapping { kana_type: "hiragana".to_string(), category: "seion".to_string(), kana: "ら".to_string(), romaji: "ra".to_string() },
-        KanaMapping { kana_type: "hiragana".to_string(), category: "seion".to_string(), kana: "り".to_string(), romaji: "ri".to_string() },
-        KanaMapping { kana_type: "hiragana".to_string(), category: "seion".to_string(), kana: "る".to_string(), romaji: "ru".to_string() },
-        KanaMapping { kana_type: "hiragana".to_string(), category: "seion".to_string(), kana: "れ".to_string(), romaji: "re".to_string() },
-        KanaMapping { kana_type: "hiragana".to_string(), category: "seion".to_string(), kana: "ろ".to_string(), romaji: "ro".to_string() },
-        KanaMapping { kana_type: "hiragana".to_string(), category: "seion".to_string(), kana: "わ".to_string(), romaji: "wa".to_string() },
-        KanaMapping { kana_type: "hiragana".to_string(), category: "seion".to_string(), kana: "を".to_string(), romaji: "wo".to_string() },
-        KanaMapping { kana_type: "hiragana".to_string(), category: "seion".to_string(), kana: "ん".to_string(), romaji: "n".to_string() },
-
-        // 片假名清音 (Katakana Seion)
-        KanaMapping { kana_type: "katakana".to_string(), category: "seion".to_string(), kana: "ア".to_string(), romaji: "a".to_string() },
-        KanaMapping { kana_type: "katakana".to_string(), category: "seion".to_string(), kana: "イ".to_string(), romaji: "i".to_string() },
-        KanaMapping { kana_type: "katakana".to_string(), category: "seion".to_string(), kana: "ウ".to_string(), romaji: "u".to_string() },
-        KanaMapping { kana_type: "katakana".to_string(), category: "seion".to_string(), kana: "エ".to_string(), romaji: "e".to_string() },
-        KanaMapping { kana_type: "katakana".to_string(), category: "seion".to_string(), kana: "オ".to_string(), romaji: "o".to_string() },
-        KanaMapping { kana_type: "katakana".to_string(), category: "seion".to_string(), kana: "カ".to_string(), romaji: "ka".to_string() },
-        KanaMapping { kana_type: "katakana".to_string(), category: "seion".to_string(), kana: "キ".to_string(), romaji: "ki".to_string() },
-        KanaMapping { kana_type: "katakana".to_string(), category: "seion".to_string(), kana: "ク".to_string(), romaji: "ku".to_string() },
-        KanaMapping { kana_type: "katakana".to_string(), category: "seion".to_string(), kana: "ケ".to_string(), romaji: "ke".to_string() },
-        KanaMapping { kana_type: "katakana".to_string(), category: "seion".to_string(), kana: "コ".to_string(), romaji: "ko".to_string() },
-        KanaMapping { kana_type: "katakana".to_string(), category: "seion".to_string(), kana: "サ".to_string(), romaji: "sa".to_string() },
-        KanaMapping { kana_type: "katakana".to_string(), category: "seion".to_string(), kana: "シ".to_string(), romaji: "shi".to_string() },
-        KanaMapping { kana_type: "katakana".to_string(), category: "seion".to_string(), kana: "ス".to_string(), romaji: "su".to_string() },
-        KanaMapping { kana_type: "katakana".to_string(), category: "seion".to_string(), kana: "セ".to_string(), romaji: "se".to_string() },
-        KanaMapping { kana_type: "katakana".to_string(), category: "seion".to_string(), kana: "ソ".to_string(), romaji: "so".to_string() },
-        KanaMapping { kana_type: "katakana".to_string(), category: "seion".to_string(), kana: "タ".to_string(), romaji: "ta".to_string() },
-        KanaMapping { kana_type: "katakana".to_string(), category: "seion".to_string(), kana: "チ".to_string(), romaji: "chi".to_string() },
-        KanaMapping { kana_type: "katakana".to_string(), category: "seion".to_string(), kana: "ツ".to_string(), romaji: "tsu".to_string() },
-        KanaMapping { kana_type: "katakana".to_string(), category: "seion".to_string(), kana: "テ".to_string(), romaji: "te".to_string() },
-        KanaMapping { kana_type: "katakana".to_string(), category: "seion".to_string(), kana: "ト".to_string(), romaji: "to".to_string() },
-        KanaMapping { kana_type: "katakana".to_string(), category: "seion".to_string(), kana: "ナ".to_string(), romaji: "na".to_string() },
-        KanaMapping { kana_type: "katakana".to_string(), category: "seion".to_string(), kana: "ニ".to_string(), romaji: "ni".to_string() },
-        KanaMapping { kana_type: "katakana".to_string(), category: "seion".to_string(), kana: "ヌ".to_string(), romaji: "nu".to_string() },
-        KanaMapping { kana_type: "katakana".to_string(), category: "seion".to_string(), kana: "ネ".to_string(), romaji: "ne".to_string() },
-        KanaMapping { kana_type: "katakana".to_string(), category: "seion".to_string(), kana: "ノ".to_string(), romaji: "no".to_string() },
-        KanaMapping { kana_type: "katakana".to_string(), category: "seion".to_string(), kana: "ハ".to_string(), romaji: "ha".to_string() },
-        KanaMapping { kana_type: "katakana".to_string(), category: "seion".to_string(), kana: "ヒ".to_string(), romaji: "hi".to_string() },
-        KanaMapping { kana_type: "katakana".to_string(), category: "seion".to_string(), kana: "フ".to_string(), romaji: "fu".to_string() },
-        KanaMapping { kana_type: "katakana".to_string(), category: "seion".to_string(), kana: "ヘ".to_string(), romaji: "he".to_string() },
-        KanaMapping { kana_type: "katakana".to_string(), category: "seion".to_string(), kana: "ホ".to_string(), romaji: "ho".to_string() },
-        KanaMapping { kana_type: "katakana".to_string(), category: "seion".to_string(), kana: "マ".to_string(), romaji: "ma".to_string() },
-        KanaMapping { kana_type: "katakana".to_string(), category: "seion".to_string(), kana: "ミ".to_string(), romaji: "mi".to_string() },
-        KanaMapping { kana_type: "katakana".to_string(), category: "seion".to_string(), kana: "ム".to_string(), romaji: "mu".to_string() },
-        KanaMapping { kana_type: "katakana".to_string(), category: "seion".to_string(), kana: "メ".to_string(), romaji: "me".to_string() },
-        KanaMapping { kana_type: "katakana".to_string(), category: "seion".to_string(), kana: "モ".to_string(), romaji: "mo".to_string() },
-        KanaMapping { kana_type: "katakana".to_string(), category: "seion".to_string(), kana: "ヤ".to_string(), romaji: "ya".to_string() },
-        KanaMapping { kana_type: "katakana".to_string(), category: "seion".to_string(), kana: "ユ".to_string(), romaji: "yu".to_string() },
-        KanaMapping { kana_type: "katakana".to_string(), category: "seion".to_string(), kana: "ヨ".to_string(), romaji: "yo".to_string() },
-        KanaMapping { kana_type: "katakana".to_string(), category: "seion".to_string(), kana: "ラ".to_string(), romaji: "ra".to_string() },
-        KanaMapping { kana_type: "katakana".to_string(), category: "seion".to_string(), kana: "リ".to_string(), romaji: "ri".to_string() },
-        KanaMapping { kana_type: "katakana".to_string(), category: "seion".to_string(), kana: "ル".to_string(), romaji: "ru".to_string() },
-        KanaMapping { kana_type: "katakana".to_string(), category: "seion".to_string(), kana: "レ".to_string(), romaji: "re".to_string() },
-        KanaMapping { kana_type: "katakana".to_string(), category: "seion".to_string(), kana: "ロ".to_string(), romaji: "ro".to_string() },
-        KanaMapping { kana_type: "katakana".to_string(), category: "seion".to_string(), kana: "ワ".to_string(), romaji: "wa".to_string() },
-        KanaMapping { kana_type: "katakana".to_string(), category: "seion".to_string(), kana: "ヲ".to_string(), romaji: "wo".to_string() },
-        KanaMapping { kana_type: "katakana".to_string(), category: "seion".to_string(), kana: "ン".to_string(), romaji: "n".to_string() },
-
-        // 平假名拗音 (Hiragana Youon)
-        KanaMapping { kana_type: "hiragana".to_string(), category: "youon".to_string(), kana: "きゃ".to_string(), romaji: "kya".to_string() },
-        KanaMapping { kana_type: "hiragana".to_string(), category: "youon".to_string(), kana: "きゅ".to_string(), romaji: "kyu".to_string() },
-        KanaMapping { kana_type: "hiragana".to_string(), category: "youon".to_string(), kana: "きょ".to_string(), romaji: "kyo".to_string() },
-        KanaMapping { kana_type: "hiragana".to_string(), category: "youon".to_string(), kana: "しゃ".to_string(), romaji: "sha".to_string() },
-        KanaMapping { kana_type: "hiragana".to_string(), category: "youon".to_string(), kana: "しゅ".to_string(), romaji: "shu".to_string() },
-        KanaMapping { kana_type: "hiragana".to_string(), category: "youon".to_string(), kana: "しょ".to_string(), romaji: "sho".to_string() },
-        KanaMapping { kana_type: "hiragana".to_string(), category: "youon".to_string(), kana: "ちゃ".to_string(), romaji: "cha".to_string() },
-        KanaMapping { kana_type: "hiragana".to_string(), category: "youon".to_string(), kana: "ちゅ".to_string(), romaji: "chu".to_string() },
-        KanaMapping { kana_type: "hiragana".to_string(), category: "youon".to_string(), kana: "ちょ".to_string(), romaji: "cho".to_string() },
-        KanaMapping { kana_type: "hiragana".to_string(), category: "youon".to_string(), kana: "にゃ".to_string(), romaji: "nya".to_string() },
-        KanaMapping { kana_type: "hiragana".to_string(), category: "youon".to_string(), kana: "にゅ".to_string(), romaji: "nyu".to_string() },
-        KanaMapping { kana_type: "hiragana".to_string(), category: "youon".to_string(), kana: "にょ".to_string(), romaji: "nyo".to_string() },
-        KanaMapping { kana_type: "hiragana".to_string(), category: "youon".to_string(), kana: "ひゃ".to_string(), romaji: "hya".to_string() },
-        KanaMapping { kana_type: "hiragana".to_string(), category: "youon".to_string(), kana: "ひゅ".to_string(), romaji: "hyu".to_string() },
-        KanaMapping { kana_type: "hiragana".to_string(), category: "youon".to_string(), kana: "ひょ".to_string(), romaji: "hyo".to_string() },
-        KanaMapping { kana_type: "hiragana".to_string(), category: "youon".to_string(), kana: "みゃ".to_string(), romaji: "mya".to_string() },
-        KanaMapping { kana_type: "hiragana".to_string(), category: "youon".to_string(), kana: "みゅ".to_string(), romaji: "myu".to_string() },
-        KanaMapping { kana_type: "hiragana".to_string(), category: "youon".to_string(), kana: "みょ".to_string(), romaji: "myo".to_string() },
-        KanaMapping { kana_type: "hiragana".to_string(), category: "youon".to_string(), kana: "りゃ".to_string(), romaji: "rya".to_string() },
-        KanaMapping { kana_type: "hiragana".to_string(), category: "youon".to_string(), kana: "りゅ".to_string(), romaji: "ryu".to_string() },
-        KanaMapping { kana_type: "hiragana".to_string(), category: "youon".to_string(), kana: "りょ".to_string(), romaji: "ryo".to_string() },
-
-        // 片假名拗音 (Katakana Youon)
-        KanaMapping { kana_type: "katakana".to_string(), category: "youon".to_string(), kana: "キャ".to_string(), romaji: "kya".to_string() },
-        KanaMapping { kana_type: "katakana".to_string(), category: "youon".to_string(), kana: "キュ".to_string(), romaji: "kyu".to_string() },
-        KanaMapping { kana_type: "katakana".to_string(), category: "youon".to_string(), kana: "キョ".to_string(), romaji: "kyo".to_string() },
-        KanaMapping { kana_type: "katakana".to_string(), category: "youon".to_string(), kana: "シャ".to_string(), romaji: "sha".to_string() },
-        KanaMapping { kana_type: "katakana".to_string(), category: "youon".to_string(), kana: "シュ".to_string(), romaji: "shu".to_string() },
-        KanaMapping { kana_type: "katakana".to_string(), category: "youon".to_string(), kana: "ショ".to_string(), romaji: "sho".to_string() },
-        KanaMapping { kana_type: "katakana".to_string(), category: "youon".to_string(), kana: "チャ".to_string(), romaji: "cha".to_string() },
-        KanaMapping { kana_type: "katakana".to_string(), category: "youon".to_string(), kana: "チュ".to_string(), romaji: "chu".to_string() },
-        KanaMapping { kana_type: "katakana".to_string(), category: "youon".to_string(), kana: "チョ".to_string(), romaji: "cho".to_string() },
-        KanaMapping { kana_type: "katakana".to_string(), category: "youon".to_string(), kana: "ニャ".to_string(), romaji: "nya".to_string() },
-        KanaMapping { kana_type: "katakana".to_string(), category: "youon".to_string(), kana: "ニュ".to_string(), romaji: "nyu".to_string() },
-        KanaMapping { kana_type: "katakana".to_string(), category: "youon".to_string(), kana: "ニョ".to_string(), romaji: "nyo".to_string() },
-        KanaMapping { kana_type: "katakana".to_string(), category: "youon".to_string(), kana: "ヒャ".to_string(), romaji: "hya".to_string() },
-        KanaMapping { kana_type: "katakana".to_string(), category: "youon".to_string(), kana: "ヒュ".to_string(), romaji: "hyu".to_string() },
-        KanaMapping { kana_type: "katakana".to_string(), category: "youon".to_string(), kana: "ヒョ".to_string(), romaji: "hyo".to_string() },
-        KanaMapping { kana_type: "katakana".to_string(), category: "youon".to_string(), kana: "ミャ".to_string(), romaji: "mya".to_string() },
-        KanaMapping { kana_type: "katakana".to_string(), category: "youon".to_string(), kana: "ミュ".to_string(), romaji: "myu".to_string() },
-        KanaMapping { kana_type: "katakana".to_string(), category: "youon".to_string(), kana: "ミョ".to_string(), romaji: "myo".to_string() },
-        KanaMapping { kana_type: "katakana".to_string(), category: "youon".to_string(), kana: "リャ".to_string(), romaji: "rya".to_string() },
-        KanaMapping { kana_type: "katakana".to_string(), category: "youon".to_string(), kana: "リュ".to_string(), romaji: "ryu".to_string() },
-        KanaMapping { kana_type: "katakana".to_string(), category: "youon".to_string(), kana: "リョ".to_string(), romaji: "ryo".to_string() },
-
-        // 平假名濁音與半濁音 (Hiragana Dakuon and Handakuon)
-        KanaMapping { kana_type: "hiragana".to_string(), category: "dakuon_handaon".to_string(), kana: "が".to_string(), romaji: "ga".to_string() },
-        KanaMapping { kana_type: "hiragana".to_string(), category: "dakuon_handaon".to_string(), kana: "ぎ".to_string(), romaji: "gi".to_string() },
-        KanaMapping { kana_type: "hiragana".to_string(), category: "dakuon_handaon".to_string(), kana: "ぐ".to_string(), romaji: "gu".to_string() },
-        KanaMapping { kana_type: "hiragana".to_string(), category: "dakuon_handaon".to_string(), kana: "げ".to_string(), romaji: "ge".to_string() },
-        KanaMapping { kana_type: "hiragana".to_string(), category: "dakuon_handaon".to_string(), kana: "ご".to_string(), romaji: "go".to_string() },
-        KanaMapping { kana_type: "hiragana".to_string(), category: "dakuon_handaon".to_string(), kana: "ざ".to_string(), romaji: "za".to_string() },
-        KanaMapping { kana_type: "hiragana".to_string(), category: "dakuon_handaon".to_string(), kana: "じ".to_string(), romaji: "ji".to_string() },
-        KanaMapping { kana_type: "hiragana".to_string(), category: "dakuon_handaon".to_string(), kana: "ず".to_string(), romaji: "zu".to_string() },
-        KanaMapping { kana_type: "hiragana".to_string(), category: "dakuon_handaon".to_string(), kana: "ぜ".to_string(), romaji: "ze".to_string() },
-        KanaMapping { kana_type: "hiragana".to_string(), category: "dakuon_handaon".to_string(), kana: "ぞ".to_string(), romaji: "zo".to_string() },
-        KanaMapping { kana_type: "hiragana".to_string(), category: "dakuon_handaon".to_string(), kana: "だ".to_string(), romaji: "da".to_string() },
-        KanaMapping { kana_type: "hiragana".to_string(), category: "dakuon_handaon".to_string(), kana: "ぢ".to_string(), romaji: "ji".to_string() },
-        KanaMapping { kana_type: "hiragana".to_string(), category: "dakuon_handaon".to_string(), kana: "づ".to_string(), romaji: "zu".to_string() },
-        KanaMapping { kana_type: "hiragana".to_string(), category: "dakuon_handaon".to_string(), kana: "で".to_string(), romaji: "de".to_string() },
-        KanaMapping { kana_type: "hiragana".to_string(), category: "dakuon_handaon".to_string(), kana: "ど".to_string(), romaji: "do".to_string() },
-        KanaMapping { kana_type: "hiragana".to_string(), category: "dakuon_handaon".to_string(), kana: "ば".to_string(), romaji: "ba".to_string() },
-        KanaMapping { kana_type: "hiragana".to_string(), category: "dakuon_handaon".to_string(), kana: "び".to_string(), romaji: "bi".to_string() },
-        KanaMapping { kana_type: "hiragana".to_string(), category: "dakuon_handaon".to_string(), kana: "ぶ".to_string(), romaji: "bu".to_string() },
-        KanaMapping { kana_type: "hiragana".to_string(), category: "dakuon_handaon".to_string(), kana: "べ".to_string(), romaji: "be".to_string() },
-        KanaMapping { kana_type: "hiragana".to_string(), category: "dakuon_handaon".to_string(), kana: "ぼ".to_string(), romaji: "bo".to_string() },
-        KanaMapping { kana_type: "hiragana".to_string(), category: "dakuon_handaon".to_string(), kana: "ぱ".to_string(), romaji: "pa".to_string() },
-        KanaMapping { kana_type: "hiragana".to_string(), category: "dakuon_handaon".to_string(), kana: "ぴ".to_string(), romaji: "pi".to_string() },
-        KanaMapping { kana_type: "hiragana".to_string(), category: "dakuon_handaon".to_string(), kana: "ぷ".to_string(), romaji: "pu".to_string() },
-        KanaMapping { kana_type: "hiragana".to_string(), category: "dakuon_handaon".to_string(), kana: "ぺ".to_string(), romaji: "pe".to_string() },
-        KanaMapping { kana_type: "hiragana".to_string(), category: "dakuon_handaon".to_string(), kana: "ぽ".to_string(), romaji: "po".to_string() },
-
-        // 片假名濁音與半濁音 (Katakana Dakuon and Handakuon)
-        KanaMapping { kana_type: "katakana".to_string(), category: "dakuon_handaon".to_string(), kana: "ガ".to_string(), romaji: "ga".to_string() },
-        KanaMapping { kana_type: "katakana".to_string(), category: "dakuon_handaon".to_string(), kana: "ギ".to_string(), romaji: "gi".to_string() },
-        KanaMapping { kana_type: "katakana".to_string(), category: "dakuon_handaon".to_string(), kana: "グ".to_string(), romaji: "gu".to_string() },
-        KanaMapping { kana_type: "katakana".to_string(), category: "dakuon_handaon".to_string(), kana: "ゲ".to_string(), romaji: "ge".to_string() },
-        KanaMapping { kana_type: "katakana".to_string(), category: "dakuon_handaon".to_string(), kana: "ゴ".to_string(), romaji: "go".to_string() },
-        KanaMapping { kana_type: "katakana".to_string(), category: "dakuon_handaon".to_string(), kana: "ザ".to_string(), romaji: "za".to_string() },
-        KanaMapping { kana_type: "katakana".to_string(), category: "dakuon_handaon".to_string(), kana: "ジ".to_string(), romaji: "ji".to_string() },
-        KanaMapping { kana_type: "katakana".to_string(), category: "dakuon_handaon".to_string(), kana: "ズ".to_string(), romaji: "zu".to_string() },
-        KanaMapping { kana_type: "katakana".to_string(), category: "dakuon_handaon".to_string(), kana: "ゼ".to_string(), romaji: "ze".to_string() },
-        KanaMapping { kana_type: "katakana".to_string(), category: "dakuon_handaon".to_string(), kana: "ゾ".to_string(), romaji: "zo".to_string() },
-        KanaMapping { kana_type: "katakana".to_string(), category: "dakuon_handaon".to_string(), kana: "ダ".to_string(), romaji: "da".to_string() },
-        KanaMapping { kana_type: "katakana".to_string(), category: "dakuon_handaon".to_string(), kana: "ヂ".to_string(), romaji: "ji".to_string() },
-        KanaMapping { kana_type: "katakana".to_string(), category: "dakuon_handaon".to_string(), kana: "ヅ".to_string(), romaji: "zu".to_string() },
-        KanaMapping { kana_type: "katakana".to_string(), category: "dakuon_handaon".to_string(), kana: "デ".to_string(), romaji: "de".to_string() },
-        KanaMapping { kana_type: "katakana".to_string(), category: "dakuon_handaon".to_string(), kana: "ド".to_string(), romaji: "do".to_string() },
-        KanaMapping { kana_type: "katakana".to_string(), category: "dakuon_handaon".to_string(), kana: "バ".to_string(), romaji: "ba".to_string() },
-        KanaMapping { kana_type: "katakana".to_string(), category: "dakuon_handaon".to_string(), kana: "ビ".to_string(), romaji: "bi".to_string() },
-        KanaMapping { kana_type: "katakana".to_string(), category: "dakuon_handaon".to_string(), kana: "ブ".to_string(), romaji: "bu".to_string() },
-        KanaMapping { kana_type: "katakana".to_string(), category: "dakuon_handaon".to_string(), kana: "ベ".to_string(), romaji: "be".to_string() },
-        KanaMapping { kana_type: "katakana".to_string(), category: "dakuon_handaon".to_string(), kana: "ボ".to_string(), romaji: "bo".to_string() },
-        KanaMapping { kana_type: "katakana".to_string(), category: "dakuon_handaon".to_string(), kana: "パ".to_string(), romaji: "pa".to_string() },
-        KanaMapping { kana_type: "katakana".to_string(), category: "dakuon_handaon".to_string(), kana: "ピ".to_string(), romaji: "pi".to_string() },
-        KanaMapping { kana_type: "katakana".to_string(), category: "dakuon_handaon".to_string(), kana: "プ".to_string(), romaji: "pu".to_string() },
-        KanaMapping { kana_type: "katakana".to_string(), category: "dakuon_handaon".to_string(), kana: "ペ".to_string(), romaji: "pe".to_string() },
-        KanaMapping { kana_type: "katakana".to_string(), category: "dakuon_handaon".to_string(), kana: "ポ".to_string(), romaji: "po".to_string() },
-    ];
-
-    // 執行批次插入
-    collection.insert_many(kana_mappings, None).await?;
-
-    println!("Inserted data successfully!");
+    // 只在指定時建立或更新集合的 $jsonSchema validator, 一般的 readWrite 使用者沒有 collMod 權限
+    if args.install_validator {
+        match validator::ensure_validator(&db, collection.name(), args.validation_level).await {
+            Ok(status) => println!("{}", status),
+            Err(e) if validator::is_unauthorized(&e) => {
+                return Err(Error::Permission(format!(
+                    "the user cannot run collMod on {}.{} to install the validator; grant the dbAdmin role or import without --install-validator",
+                    target.database, target.collection
+                )))
+            }
+            Err(e) => return Err(Error::Write(e)),
+        }
+    }
+
+    // 唯一索引建立前先移除重複的文件, 例如以舊版 importer 重複匯入的資料
+    let duplicates =
+        index::remove_duplicates(&collection, &target.retry, target.batch_size).await.map_err(Error::Write)?;
+    if duplicates > 0 {
+        println!("Removed {} duplicate documents.", duplicates);
+    }
+
+    // 確保索引存在, 唯一索引可避免重複的假名
+    for report in index::ensure_indexes(&collection).await.map_err(Error::Write)? {
+        match report.status {
+            index::IndexStatus::Conflict(_) => eprintln!("Warning: {}", report),
+            _ => println!("{}", report),
+        }
+    }
+
+    // validator 與索引在 transaction 之外建立; replica set 與 sharded cluster 上以 transaction 寫入, standalone 時退回一般寫入
+    let transactional = !args.no_transaction
+        && match health::topology(collection.client()).await.map_err(Error::Connection)? {
+            Topology::Standalone => {
+                eprintln!("Warning: transactions require a replica set or sharded cluster, importing without a transaction.");
+                false
+            }
+            _ => true,
+        };
+
+    // 分批寫入資料
+    let options = ImportOptions {
+        mode: args.mode,
+        prune: args.prune,
+        batch_size: target.batch_size,
+        ordered: !args.unordered,
+        retry: target.retry,
+    };
+    let summary = if transactional {
+        import::import_in_transaction(&collection, &mappings, &options).await
+    } else {
+        import::import(&collection, mappings, &options).await
+    }
+    .map_err(Error::Write)?;
+    if summary.failed.is_empty() {
+        println!("Imported data successfully: {}.", summary);
+        return Ok(());
+    }
+
+    // 以資料集的行號列出寫入失敗的文件
+    // 重複文件的清除在 transaction 之前完成, 不會隨 transaction 復原
+    if transactional && duplicates > 0 {
+        println!("Transaction aborted, no dataset changes were written; the {} duplicate documents removed before it stay removed.", duplicates);
+    } else if transactional {
+        println!("Transaction aborted, the collection was left unchanged.");
+    } else {
+        println!("Imported data with errors: {}.", summary);
+    }
+    let positions = dataset.positions();
+    for failure in &summary.failed {
+        let position = positions.get(&(failure.kana_type, failure.kana.clone())).map_or(dataset.source.as_str(), String::as_str);
+        eprintln!("{}: {}", position, failure);
+    }
+    Err(Error::Rejected(format!("{} documents were rejected by the server", summary.failed.len())))
+}
+
+async fn run_export(target: &Target, args: ExportArgs) -> Result<()> {
+    let (_, collection) = target.open().await?;
+    let mappings: Vec<KanaMapping> = read_existing(&collection).await?.into_iter().map(|existing| existing.mapping).collect();
+
+    match &args.to {
+        Some(path) => {
+            dataset::save(&mappings, path, args.format)?;
+            eprintln!("Exported {} kana mappings to {}.", mappings.len(), path.display());
+        }
+        None => dataset::write(&mappings, args.format.unwrap_or(Format::Json), &mut io::stdout().lock(), "stdout")?,
+    }
+    Ok(())
+}
+
+// 集合內容與資料集不一致時以驗證失敗結束
+async fn run_verify(target: &Target, args: DatasetArgs) -> Result<()> {
+    let kana_mappings = load_dataset(target, &args)?.mappings();
+    let (_, collection) = target.open().await?;
+
+    let plan = plan::compute(read_existing(&collection).await?, &kana_mappings, true);
+    if !plan.is_empty() {
+        println!("{}", plan);
+        return Err(Error::Validation(format!(
+            "{}.{} does not match the dataset",
+            target.database, target.collection
+        )));
+    }
+    println!("{}.{} matches the dataset ({} documents).", target.database, target.collection, plan.unchanged);
+    Ok(())
+}
+
+// 只讀取集合並印出變更計畫, 不寫入任何資料
+async fn run_diff(
+    target: &Target,
+    kana_mappings: Vec<KanaMapping>,
+    mode: ImportMode,
+    prune: bool,
+    output: OutputFormat,
+) -> Result<()> {
+    let plan = match mode {
+        ImportMode::Insert => Plan { inserts: kana_mappings, ..Default::default() },
+        ImportMode::Upsert => {
+            let (_, collection) = target.open().await?;
+            plan::compute(read_existing(&collection).await?, &kana_mappings, prune)
+        }
+    };
+
+    match output {
+        OutputFormat::Text => println!("{}", plan),
+        OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&plan).expect("plan serializes to JSON")),
+    }
     Ok(())
 }
+
+async fn run_stats(target: &Target) -> Result<()> {
+    let (_, collection) = target.open().await?;
+    println!("{}", stats::collect(&collection).await.map_err(Error::Connection)?);
+    Ok(())
+}
+
+// 連線失敗或缺少權限時以非零代碼結束
+async fn run_check(target: &Target) -> Result<()> {
+    let client = connection::connect(&target.connection, &target.retry).await?;
+    let report = health::check(&client, &target.database, &target.collection).await.map_err(Error::Connection)?;
+    println!("{}", report);
+
+    match report.privileges.map(|privileges| privileges.missing()) {
+        Some(missing) if !missing.is_empty() => Err(Error::Permission(format!(
+            "the user cannot {} on {}.{}",
+            missing.join(" or "),
+            target.database,
+            target.collection
+        ))),
+        _ => Ok(()),
+    }
+}
+
+async fn run_drop(target: &Target, args: DropArgs) -> Result<()> {
+    if !args.yes {
+        return Err(Error::Usage(format!(
+            "refusing to drop {}.{} without --yes",
+            target.database, target.collection
+        )));
+    }
+    let (_, collection) = target.open().await?;
+    collection.drop(None).await.map_err(Error::Write)?;
+    println!("Dropped {}.{}.", target.database, target.collection);
+    Ok(())
+}
+
+// 終端機上以不回顯的提示輸入密碼, 否則從 stdin 讀取一行, 例如 `cat secret | kana_data_importer --ask-password`
+fn read_password() -> Result<String> {
+    let stdin = io::stdin();
+    let password = if stdin.is_terminal() {
+        rpassword::prompt_password("MongoDB password: ")
+    } else {
+        let mut line = String::new();
+        stdin.read_line(&mut line).map(|_| line.trim_end_matches(['\r', '\n']).to_string())
+    };
+    let invalid = match password {
+        Ok(password) if !password.is_empty() => return Ok(password),
+        Ok(_) => "the password must not be empty".to_string(),
+        Err(e) => format!("cannot read the password: {}", e),
+    };
+    Err(Error::Config(ConfigError { invalid: vec![invalid], ..Default::default() }))
+}
+
+// --from 優先於設定中的資料集
+fn load_dataset(target: &Target, args: &DatasetArgs) -> Result<Dataset> {
+    let dataset = match args.from.as_ref().or(target.dataset.as_ref()) {
+        Some(path) => Dataset::open(path, args.format)?,
+        None => Dataset::builtin()?,
+    };
+    Ok(dataset)
+}
+
+// 集合中無法解析的文件 (例如未知的 kana_type) 視為驗證失敗
+async fn read_existing(collection: &Collection<KanaMapping>) -> Result<Vec<plan::Existing>> {
+    plan::read_existing(collection).await.map_err(|e| match *e.kind {
+        ErrorKind::BsonDeserialization(ref error) => Error::Validation(format!("cannot read a document: {}", error)),
+        _ => Error::Connection(e),
+    })
+}
@@ -0,0 +1,343 @@
+use mongodb::bson::Bson;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::str::FromStr;
+
+// 羅馬拼音只允許小寫英文字母; 促音, 長音符號, 濁點與疊字符號沒有固定讀音, 由 Role 允許空字串
+pub const ROMAJI_PATTERN: &str = "^[a-z]+$";
+
+// KanaMapping 中可省略的具型別欄位, 未設定時不會寫入文件
+pub const OPTIONAL_FIELDS: [&str; 5] = ["usage", "role", "fullwidth", "modern_kana", "beginner"];
+
+// 序列化後的字串即為 MongoDB 中儲存的值, 不可任意更改
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum KanaType {
+    Hiragana,
+    Katakana,
+    HalfwidthKatakana,
+}
+
+impl KanaType {
+    pub const ALL: [KanaType; 3] = [KanaType::Hiragana, KanaType::Katakana, KanaType::HalfwidthKatakana];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            KanaType::Hiragana => "hiragana",
+            KanaType::Katakana => "katakana",
+            KanaType::HalfwidthKatakana => "halfwidth_katakana",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Category {
+    Seion,
+    Dakuon,
+    Handakuon,
+    // 既有資料將濁音與半濁音合併為同一類, 保留原本的字串以相容
+    #[serde(rename = "dakuon_handaon")]
+    DakuonHandaon,
+    Youon,
+    // 小寫假名: 小寫母音與拗音用的ゃゅょゎ
+    SmallKana,
+    Sokuon,
+    // 長音符號ー
+    Choonpu,
+    // 半形片假名分開表示的濁點ﾞ與半濁點ﾟ
+    VoicingMark,
+    // 外來語用的片假名組合, 依內閣告示「外来語の表記」
+    ExtendedKatakana,
+    // 現代已不使用的假名與疊字符號, 見於古典文本與人名
+    Archaic,
+}
+
+impl Category {
+    pub const ALL: [Category; 11] = [
+        Category::Seion,
+        Category::Dakuon,
+        Category::Handakuon,
+        Category::DakuonHandaon,
+        Category::Youon,
+        Category::SmallKana,
+        Category::Sokuon,
+        Category::Choonpu,
+        Category::VoicingMark,
+        Category::ExtendedKatakana,
+        Category::Archaic,
+    ];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Category::Seion => "seion",
+            Category::Dakuon => "dakuon",
+            Category::Handakuon => "handakuon",
+            Category::DakuonHandaon => "dakuon_handaon",
+            Category::Youon => "youon",
+            Category::SmallKana => "small_kana",
+            Category::Sokuon => "sokuon",
+            Category::Choonpu => "choonpu",
+            Category::VoicingMark => "voicing_mark",
+            Category::ExtendedKatakana => "extended_katakana",
+            Category::Archaic => "archaic",
+        }
+    }
+
+    // 小寫假名與符號必須以 role 標明作用
+    pub fn requires_role(self) -> bool {
+        matches!(self, Category::SmallKana | Category::Sokuon | Category::Choonpu | Category::VoicingMark)
+    }
+}
+
+// 外來語表記的使用範圍: standard 為告示第 1 表的一般用法, rare 為第 2 表中貼近原音的寫法
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Usage {
+    Standard,
+    Rare,
+}
+
+impl Usage {
+    pub const ALL: [Usage; 2] = [Usage::Standard, Usage::Rare];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Usage::Standard => "standard",
+            Usage::Rare => "rare",
+        }
+    }
+}
+
+// 小寫假名與符號在轉寫時的作用
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Role {
+    // ぁぃぅぇぉ: 與前一個假名組成新的音節, 如ファ
+    SmallVowel,
+    // ゃゅょゎ: 拗音的第二個字, 如きゃ
+    YouonGlide,
+    // っ: 重複下一個音節的子音
+    Sokuon,
+    // ー: 拉長前一個母音
+    LongVowelMark,
+    // ﾞ: 將前一個假名變為濁音, 如ｶﾞ
+    Dakuten,
+    // ﾟ: 將前一個假名變為半濁音, 如ﾊﾟ
+    Handakuten,
+    // ゝヽ: 重複前一個假名, 如いすゝ
+    IterationMark,
+    // ゞヾ: 重複前一個假名並變為濁音, 如みすゞ
+    VoicedIterationMark,
+}
+
+impl Role {
+    pub const ALL: [Role; 8] = [
+        Role::SmallVowel,
+        Role::YouonGlide,
+        Role::Sokuon,
+        Role::LongVowelMark,
+        Role::Dakuten,
+        Role::Handakuten,
+        Role::IterationMark,
+        Role::VoicedIterationMark,
+    ];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Role::SmallVowel => "small_vowel",
+            Role::YouonGlide => "youon_glide",
+            Role::Sokuon => "sokuon",
+            Role::LongVowelMark => "long_vowel_mark",
+            Role::Dakuten => "dakuten",
+            Role::Handakuten => "handakuten",
+            Role::IterationMark => "iteration_mark",
+            Role::VoicedIterationMark => "voiced_iteration_mark",
+        }
+    }
+
+    // 具有此作用的假名所屬的分類
+    pub fn category(self) -> Category {
+        match self {
+            Role::SmallVowel | Role::YouonGlide => Category::SmallKana,
+            Role::Sokuon => Category::Sokuon,
+            Role::LongVowelMark => Category::Choonpu,
+            Role::Dakuten | Role::Handakuten => Category::VoicingMark,
+            Role::IterationMark | Role::VoicedIterationMark => Category::Archaic,
+        }
+    }
+
+    // 促音, 長音符號, 濁點與疊字符號只改變或重複相鄰的音節, 本身沒有羅馬拼音
+    pub fn has_romaji(self) -> bool {
+        matches!(self, Role::SmallVowel | Role::YouonGlide)
+    }
+}
+
+impl fmt::Display for KanaType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl fmt::Display for Category {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl fmt::Display for Usage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl fmt::Display for Role {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for KanaType {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        KanaType::ALL.into_iter().find(|kana_type| kana_type.as_str() == s).ok_or_else(|| {
+            let expected: Vec<&str> = KanaType::ALL.iter().map(|kana_type| kana_type.as_str()).collect();
+            format!("unknown kana_type `{}` (expected one of {})", s, expected.join(", "))
+        })
+    }
+}
+
+impl FromStr for Category {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Category::ALL.into_iter().find(|category| category.as_str() == s).ok_or_else(|| {
+            let expected: Vec<&str> = Category::ALL.iter().map(|category| category.as_str()).collect();
+            format!("unknown category `{}` (expected one of {})", s, expected.join(", "))
+        })
+    }
+}
+
+impl FromStr for Usage {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Usage::ALL.into_iter().find(|usage| usage.as_str() == s).ok_or_else(|| {
+            let expected: Vec<&str> = Usage::ALL.iter().map(|usage| usage.as_str()).collect();
+            format!("unknown usage `{}` (expected one of {})", s, expected.join(", "))
+        })
+    }
+}
+
+impl FromStr for Role {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Role::ALL.into_iter().find(|role| role.as_str() == s).ok_or_else(|| {
+            let expected: Vec<&str> = Role::ALL.iter().map(|role| role.as_str()).collect();
+            format!("unknown role `{}` (expected one of {})", s, expected.join(", "))
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct KanaMapping {
+    pub kana_type: KanaType,
+    pub category: Category,
+    pub kana: String,
+    pub romaji: String,
+    // 只用於 extended_katakana
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub usage: Option<Usage>,
+    // 只用於 small_kana, sokuon, choonpu, voicing_mark 與 archaic 的疊字符號
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub role: Option<Role>,
+    // 半形片假名對應的全形寫法, 供搜尋時正規化
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fullwidth: Option<String>,
+    // archaic 假名的現代寫法, 如ゐ讀作い
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub modern_kana: Option<String>,
+    // false 表示初學者練習中應隱藏, 未設定時視為 true; 查詢時仍可取得
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub beginner: Option<bool>,
+    // 資料檔中除了上述欄位以外的欄位, 原樣寫入 MongoDB
+    #[serde(flatten, default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub extra: BTreeMap<String, Bson>,
+}
+
+impl KanaMapping {
+    // 只有必要欄位的對應, 選用欄位以 struct update 語法補上
+    pub fn new(kana_type: KanaType, category: Category, kana: &str, romaji: &str) -> Self {
+        KanaMapping {
+            kana_type,
+            category,
+            kana: kana.to_string(),
+            romaji: romaji.to_string(),
+            usage: None,
+            role: None,
+            fullwidth: None,
+            modern_kana: None,
+            beginner: None,
+            extra: BTreeMap::new(),
+        }
+    }
+
+    // 自然鍵: 同一種假名類型中每個假名只會出現一次
+    pub fn key(&self) -> (KanaType, &str) {
+        (self.kana_type, &self.kana)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mongodb::bson::{self, doc};
+
+    // 既有集合中的字串值必須維持不變
+    #[test]
+    fn serializes_to_the_stored_strings() {
+        let mapping = KanaMapping {
+            fullwidth: Some("ガ".to_string()),
+            ..KanaMapping::new(KanaType::HalfwidthKatakana, Category::DakuonHandaon, "ｶﾞ", "ga")
+        };
+        let document = doc! {
+            "kana_type": "halfwidth_katakana",
+            "category": "dakuon_handaon",
+            "kana": "ｶﾞ",
+            "romaji": "ga",
+            "fullwidth": "ガ",
+        };
+        assert_eq!(bson::to_document(&mapping).unwrap(), document);
+        assert_eq!(bson::from_document::<KanaMapping>(document).unwrap(), mapping);
+    }
+
+    #[test]
+    fn serde_and_from_str_use_the_same_strings() {
+        for kana_type in KanaType::ALL {
+            assert_eq!(bson::to_bson(&kana_type).unwrap(), Bson::String(kana_type.as_str().to_string()));
+        }
+        for category in Category::ALL {
+            assert_eq!(bson::to_bson(&category).unwrap(), Bson::String(category.as_str().to_string()));
+        }
+        for usage in Usage::ALL {
+            assert_eq!(bson::to_bson(&usage).unwrap(), Bson::String(usage.as_str().to_string()));
+        }
+        for role in Role::ALL {
+            assert_eq!(bson::to_bson(&role).unwrap(), Bson::String(role.as_str().to_string()));
+        }
+    }
+
+    #[test]
+    fn rejects_unknown_values_on_read() {
+        let document = doc! { "kana_type": "hiragama", "category": "seion", "kana": "あ", "romaji": "a" };
+        let error = bson::from_document::<KanaMapping>(document).unwrap_err().to_string();
+        assert!(error.contains("unknown variant `hiragama`"), "{}", error);
+
+        let document = doc! { "kana_type": "katakana", "category": "dakuon-handaon", "kana": "ガ", "romaji": "ga" };
+        let error = bson::from_document::<KanaMapping>(document).unwrap_err().to_string();
+        assert!(error.contains("unknown variant `dakuon-handaon`"), "{}", error);
+    }
+}
@@ -0,0 +1,26 @@
+// 設定與資料集共用的字串解析
+
+// 環境變數與資料集的布林值, 不分大小寫
+pub fn parse_bool(s: &str) -> Result<bool, String> {
+    match s.to_ascii_lowercase().as_str() {
+        "true" | "1" | "yes" => Ok(true),
+        "false" | "0" | "no" => Ok(false),
+        _ => Err(format!("invalid boolean `{}` (expected true or false)", s)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_booleans_case_insensitively() {
+        for value in ["true", "TRUE", "1", "yes"] {
+            assert_eq!(parse_bool(value), Ok(true));
+        }
+        for value in ["false", "False", "0", "no"] {
+            assert_eq!(parse_bool(value), Ok(false));
+        }
+        assert_eq!(parse_bool("maybe"), Err("invalid boolean `maybe` (expected true or false)".to_string()));
+    }
+}
@@ -0,0 +1,306 @@
+use crate::model::{KanaMapping, KanaType, OPTIONAL_FIELDS};
+use futures::TryStreamExt;
+use mongodb::{
+    bson::{self, doc, Bson, Document},
+    options::FindOptions,
+    ClientSession, Collection,
+};
+use serde::Serialize;
+use std::cmp::Ordering;
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::fmt;
+use std::str::FromStr;
+
+// 集合中現有的文件, 保留 _id 以便精確地更新或刪除
+#[derive(Debug, Clone, Serialize)]
+pub struct Existing {
+    #[serde(rename = "_id")]
+    pub id: Bson,
+    #[serde(flatten)]
+    pub mapping: KanaMapping,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FieldChange {
+    pub field: String,
+    pub before: Option<Bson>,
+    pub after: Option<Bson>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Update {
+    #[serde(skip)]
+    pub id: Bson,
+    pub before: KanaMapping,
+    pub after: KanaMapping,
+    pub changes: Vec<FieldChange>,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct Plan {
+    pub inserts: Vec<KanaMapping>,
+    pub updates: Vec<Update>,
+    pub deletes: Vec<Existing>,
+    pub unchanged: usize,
+}
+
+impl Plan {
+    pub fn is_empty(&self) -> bool {
+        self.inserts.is_empty() && self.updates.is_empty() && self.deletes.is_empty()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputFormat {
+    Text,
+    Json,
+}
+
+impl FromStr for OutputFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "text" => Ok(OutputFormat::Text),
+            "json" => Ok(OutputFormat::Json),
+            _ => Err(format!("unknown output format `{}` (expected text or json)", s)),
+        }
+    }
+}
+
+// 依 _id 排序讀取, 重複的文件與 index::remove_duplicates 一樣保留 _id 最小的一份
+pub async fn read_existing(collection: &Collection<KanaMapping>) -> mongodb::error::Result<Vec<Existing>> {
+    let documents: Vec<Document> =
+        collection.clone_with_type::<Document>().find(None, by_id()).await?.try_collect().await?;
+    from_documents(documents)
+}
+
+// 在 transaction 中讀取, 與之後的寫入看到同一份快照
+pub async fn read_existing_with_session(
+    collection: &Collection<KanaMapping>,
+    session: &mut ClientSession,
+) -> mongodb::error::Result<Vec<Existing>> {
+    let mut cursor = collection.clone_with_type::<Document>().find_with_session(None, by_id(), session).await?;
+    let documents: Vec<Document> = cursor.stream(session).try_collect().await?;
+    from_documents(documents)
+}
+
+fn by_id() -> FindOptions {
+    FindOptions::builder().sort(doc! { "_id": 1 }).build()
+}
+
+fn from_documents(documents: Vec<Document>) -> mongodb::error::Result<Vec<Existing>> {
+    documents
+        .into_iter()
+        .map(|mut document| {
+            let id = document.remove("_id").unwrap_or(Bson::Null);
+            let mapping = bson::from_document(document)?;
+            Ok(Existing { id, mapping })
+        })
+        .collect()
+}
+
+// 比較集合現況與資料集並產生變更計畫:
+// - 資料集中有而集合中沒有的文件列為新增, 匯入會寫入的欄位內容不同的列為更新
+// - 同一個鍵在集合中有多份時只保留 _id 最小的一份, 其餘列為刪除
+// - prune 為 true 時, 集合中有而資料集中沒有的文件也列為刪除
+pub fn compute(mut existing: Vec<Existing>, desired: &[KanaMapping], prune: bool) -> Plan {
+    existing.sort_by(|a, b| compare_ids(&a.id, &b.id));
+    let desired_keys: HashSet<(KanaType, &str)> = desired.iter().map(KanaMapping::key).collect();
+
+    let mut plan = Plan::default();
+    let mut seen: HashSet<(KanaType, String)> = HashSet::new();
+    let mut current: HashMap<(KanaType, String), Existing> = HashMap::new();
+    let mut extra = Vec::new();
+    for existing in existing {
+        let (kana_type, kana) = existing.mapping.key();
+        let key = (kana_type, kana.to_string());
+        if !seen.insert(key.clone()) {
+            plan.deletes.push(existing);
+        } else if desired_keys.contains(&existing.mapping.key()) {
+            current.insert(key, existing);
+        } else {
+            extra.push(existing);
+        }
+    }
+
+    for mapping in desired {
+        let (kana_type, kana) = mapping.key();
+        match current.remove(&(kana_type, kana.to_string())) {
+            None => plan.inserts.push(mapping.clone()),
+            Some(existing) => {
+                let changes = field_changes(&existing.mapping, mapping);
+                if changes.is_empty() {
+                    plan.unchanged += 1;
+                } else {
+                    plan.updates.push(Update { changes, id: existing.id, before: existing.mapping, after: mapping.clone() });
+                }
+            }
+        }
+    }
+
+    if prune {
+        plan.deletes.extend(extra);
+    }
+    plan
+}
+
+// 依 MongoDB 的 BSON 比較順序排列常見的 _id 型別 (數字, 字串, ObjectId), 其他型別維持讀取時的順序
+fn compare_ids(a: &Bson, b: &Bson) -> Ordering {
+    fn rank(id: &Bson) -> u8 {
+        match id {
+            Bson::Null => 0,
+            Bson::Int32(_) | Bson::Int64(_) | Bson::Double(_) => 1,
+            Bson::String(_) => 2,
+            Bson::ObjectId(_) => 3,
+            _ => 4,
+        }
+    }
+    fn number(id: &Bson) -> f64 {
+        match *id {
+            Bson::Int32(n) => n as f64,
+            Bson::Int64(n) => n as f64,
+            Bson::Double(n) => n,
+            _ => 0.0,
+        }
+    }
+    rank(a).cmp(&rank(b)).then_with(|| match (a, b) {
+        (Bson::String(a), Bson::String(b)) => a.cmp(b),
+        (Bson::ObjectId(a), Bson::ObjectId(b)) => a.bytes().cmp(&b.bytes()),
+        _ => number(a).total_cmp(&number(b)),
+    })
+}
+
+// 只比較 upsert 會寫入的欄位: 資料集中的欄位與選用欄位, 其他應用程式加入的欄位不列為差異
+fn field_changes(before: &KanaMapping, after: &KanaMapping) -> Vec<FieldChange> {
+    let before = bson::to_document(before).unwrap_or_default();
+    let after = bson::to_document(after).unwrap_or_default();
+    let fields: BTreeSet<&str> = after.keys().map(String::as_str).chain(OPTIONAL_FIELDS).collect();
+    fields
+        .into_iter()
+        .filter(|field| before.get(field) != after.get(field))
+        .map(|field| FieldChange {
+            field: field.to_string(),
+            before: before.get(field).cloned(),
+            after: after.get(field).cloned(),
+        })
+        .collect()
+}
+
+impl fmt::Display for Plan {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_empty() {
+            return write!(f, "No changes, {} documents unchanged.", self.unchanged);
+        }
+        for mapping in &self.inserts {
+            writeln!(f, "+ {} {} ({}, {})", mapping.kana_type, mapping.kana, mapping.category, mapping.romaji)?;
+        }
+        for update in &self.updates {
+            writeln!(f, "~ {} {}", update.after.kana_type, update.after.kana)?;
+            for change in &update.changes {
+                let show = |value: &Option<Bson>| value.as_ref().map_or("(none)".to_string(), Bson::to_string);
+                writeln!(f, "    {}: {} -> {}", change.field, show(&change.before), show(&change.after))?;
+            }
+        }
+        for existing in &self.deletes {
+            let mapping = &existing.mapping;
+            writeln!(f, "- {} {} ({}, {})", mapping.kana_type, mapping.kana, mapping.category, mapping.romaji)?;
+        }
+        write!(
+            f,
+            "Plan: {} to insert, {} to update, {} to delete, {} unchanged.",
+            self.inserts.len(),
+            self.updates.len(),
+            self.deletes.len(),
+            self.unchanged
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::{Category, Usage};
+    use mongodb::bson::doc;
+
+    fn existing(id: i32, mapping: KanaMapping) -> Existing {
+        Existing { id: Bson::Int32(id), mapping }
+    }
+
+    fn deleted_ids(plan: &Plan) -> Vec<Bson> {
+        plan.deletes.iter().map(|existing| existing.id.clone()).collect()
+    }
+
+    #[test]
+    fn compute_classifies_each_document() {
+        let a = KanaMapping::new(KanaType::Hiragana, Category::Seion, "あ", "a");
+        let i = KanaMapping::new(KanaType::Hiragana, Category::Seion, "い", "i");
+        let u = KanaMapping::new(KanaType::Hiragana, Category::Seion, "う", "u");
+        let a_katakana = KanaMapping::new(KanaType::Katakana, Category::Seion, "ア", "a");
+        let desired = [a.clone(), i.clone(), u.clone()];
+
+        // (名稱, 集合現況, prune, 新增, 更新, 刪除的 _id, 未變更)
+        let cases = vec![
+            ("empty collection", vec![], false, 3, 0, vec![], 0),
+            ("identical", vec![existing(1, a.clone()), existing(2, i.clone()), existing(3, u.clone())], false, 0, 0, vec![], 3),
+            ("changed romaji", vec![existing(1, KanaMapping::new(KanaType::Hiragana, Category::Seion, "あ", "x")), existing(2, i.clone())], false, 1, 1, vec![], 1),
+            ("same kana, other kana_type", vec![existing(1, KanaMapping::new(KanaType::Katakana, Category::Seion, "あ", "a"))], false, 3, 0, vec![], 0),
+            ("duplicates keep the first", vec![existing(1, a.clone()), existing(2, a.clone()), existing(3, a.clone())], false, 2, 0, vec![2, 3], 1),
+            // 讀取順序不是 _id 順序時, 仍保留 _id 最小 (內容不同) 的一份
+            ("duplicates out of order keep the smallest _id", vec![existing(3, a.clone()), existing(1, KanaMapping::new(KanaType::Hiragana, Category::Seion, "あ", "x")), existing(2, a.clone())], false, 2, 1, vec![2, 3], 0),
+            ("extra kept without prune", vec![existing(1, a.clone()), existing(9, a_katakana.clone())], false, 2, 0, vec![], 1),
+            ("extra deleted with prune", vec![existing(1, a.clone()), existing(9, a_katakana.clone())], true, 2, 0, vec![9], 1),
+            ("duplicate extra without prune", vec![existing(8, a_katakana.clone()), existing(9, a_katakana.clone())], false, 3, 0, vec![9], 0),
+            ("duplicate extra with prune", vec![existing(8, a_katakana.clone()), existing(9, a_katakana.clone())], true, 3, 0, vec![9, 8], 0),
+        ];
+        for (name, existing, prune, inserts, updates, deletes, unchanged) in cases {
+            let plan = compute(existing, &desired, prune);
+            assert_eq!(plan.inserts.len(), inserts, "{}: inserts", name);
+            assert_eq!(plan.updates.len(), updates, "{}: updates", name);
+            assert_eq!(deleted_ids(&plan), deletes.into_iter().map(Bson::Int32).collect::<Vec<_>>(), "{}: deletes", name);
+            assert_eq!(plan.unchanged, unchanged, "{}: unchanged", name);
+            assert_eq!(plan.is_empty(), inserts + updates == 0 && plan.deletes.is_empty(), "{}: is_empty", name);
+        }
+    }
+
+    #[test]
+    fn compare_ids_orders_object_ids_by_creation() {
+        let older = Bson::ObjectId(bson::oid::ObjectId::parse_str("65a000000000000000000001").unwrap());
+        let newer = Bson::ObjectId(bson::oid::ObjectId::parse_str("65b000000000000000000000").unwrap());
+        assert_eq!(compare_ids(&older, &newer), Ordering::Less);
+        assert_eq!(compare_ids(&Bson::Int64(2), &Bson::Int32(10)), Ordering::Less);
+        assert_eq!(compare_ids(&Bson::String("a".to_string()), &older), Ordering::Less);
+    }
+
+    #[test]
+    fn compute_lists_field_changes_of_an_update() {
+        let before = KanaMapping { usage: Some(Usage::Rare), ..KanaMapping::new(KanaType::Katakana, Category::Seion, "ヴ", "bu") };
+        let after = KanaMapping::new(KanaType::Katakana, Category::Seion, "ヴ", "vu");
+        let plan = compute(vec![existing(1, before)], &[after], false);
+
+        let update = &plan.updates[0];
+        assert_eq!(update.id, Bson::Int32(1));
+        let changes: Vec<(&str, Option<&Bson>, Option<&Bson>)> = update
+            .changes
+            .iter()
+            .map(|change| (change.field.as_str(), change.before.as_ref(), change.after.as_ref()))
+            .collect();
+        assert_eq!(
+            changes,
+            [
+                ("romaji", Some(&Bson::String("bu".to_string())), Some(&Bson::String("vu".to_string()))),
+                ("usage", Some(&Bson::String("rare".to_string())), None),
+            ]
+        );
+    }
+
+    // 其他應用程式加入的欄位不會被 upsert 覆寫, 因此不算差異
+    #[test]
+    fn compute_ignores_fields_the_dataset_does_not_set() {
+        let mut stored = KanaMapping::new(KanaType::Hiragana, Category::Seion, "あ", "a");
+        stored.extra = doc! { "audio": "a.mp3" }.into_iter().collect();
+        let plan = compute(vec![existing(1, stored)], &[KanaMapping::new(KanaType::Hiragana, Category::Seion, "あ", "a")], false);
+        assert!(plan.is_empty());
+        assert_eq!(plan.unchanged, 1);
+    }
+}
@@ -0,0 +1,163 @@
+use mongodb::error::{ErrorKind, RETRYABLE_WRITE_ERROR, TRANSIENT_TRANSACTION_ERROR};
+use rand::Rng;
+use std::fmt;
+use std::future::Future;
+use std::time::Duration;
+
+pub const DEFAULT_MAX_RETRIES: u32 = 3;
+
+// 暫時性錯誤的重試策略: 指數退避並加上隨機抖動, 避免多個 importer 同時重試
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    pub max_retries: u32,
+    pub initial_delay: Duration,
+    pub max_delay: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            max_retries: DEFAULT_MAX_RETRIES,
+            initial_delay: Duration::from_millis(200),
+            max_delay: Duration::from_secs(10),
+        }
+    }
+}
+
+// 可判斷是否值得重試的錯誤
+pub trait Transient {
+    fn is_transient(&self) -> bool;
+}
+
+// 帶有 TransientTransactionError / RetryableWriteError 標籤、網路錯誤或找不到可用的伺服器
+impl Transient for mongodb::error::Error {
+    fn is_transient(&self) -> bool {
+        self.contains_label(TRANSIENT_TRANSACTION_ERROR)
+            || self.contains_label(RETRYABLE_WRITE_ERROR)
+            || matches!(
+                *self.kind,
+                ErrorKind::Io(_) | ErrorKind::ConnectionPoolCleared { .. } | ErrorKind::ServerSelection { .. }
+            )
+    }
+}
+
+impl Transient for crate::Error {
+    fn is_transient(&self) -> bool {
+        match self {
+            crate::Error::Connection(e) | crate::Error::Write(e) => e.is_transient(),
+            _ => false,
+        }
+    }
+}
+
+impl RetryPolicy {
+    // 第 retry 次重試前的等待時間: 上限內的 initial_delay * 2^retry, 取其一半再加上隨機的另一半
+    pub fn delay(&self, retry: u32) -> Duration {
+        let ceiling = self.initial_delay.saturating_mul(2u32.saturating_pow(retry)).min(self.max_delay);
+        let half = ceiling / 2;
+        half + half.mul_f64(rand::thread_rng().gen_range(0.0..=1.0))
+    }
+
+    // operation 會收到目前是第幾次嘗試 (0 為第一次), 每次重試都會寫到 stderr
+    pub async fn run<T, E, F, Fut>(&self, operation: &str, mut f: F) -> Result<T, E>
+    where
+        E: Transient + fmt::Display,
+        F: FnMut(u32) -> Fut,
+        Fut: Future<Output = Result<T, E>>,
+    {
+        let mut attempt = 0;
+        loop {
+            match f(attempt).await {
+                Err(e) if attempt < self.max_retries && e.is_transient() => {
+                    let delay = self.delay(attempt);
+                    attempt += 1;
+                    eprintln!(
+                        "Warning: {} failed, retrying in {} ms ({}/{}): {}",
+                        operation,
+                        delay.as_millis(),
+                        attempt,
+                        self.max_retries,
+                        e
+                    );
+                    tokio::time::sleep(delay).await;
+                }
+                result => return result,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+
+    fn policy(max_retries: u32) -> RetryPolicy {
+        RetryPolicy { max_retries, initial_delay: Duration::ZERO, max_delay: Duration::ZERO }
+    }
+
+    fn network_error() -> crate::Error {
+        let io = std::io::Error::new(std::io::ErrorKind::ConnectionReset, "connection reset");
+        crate::Error::Connection(ErrorKind::from(io).into())
+    }
+
+    #[test]
+    fn delay_stays_between_half_and_full_ceiling() {
+        let policy = RetryPolicy { max_retries: 5, initial_delay: Duration::from_millis(100), max_delay: Duration::from_secs(10) };
+        for (retry, ceiling) in [(0, 100), (1, 200), (2, 400), (3, 800)] {
+            for _ in 0..100 {
+                let delay = policy.delay(retry);
+                assert!(delay >= Duration::from_millis(ceiling / 2) && delay <= Duration::from_millis(ceiling), "{:?}", delay);
+            }
+        }
+    }
+
+    #[test]
+    fn delay_is_capped_at_max_delay() {
+        let policy = RetryPolicy { max_retries: 64, initial_delay: Duration::from_millis(100), max_delay: Duration::from_secs(1) };
+        for retry in [4, 10, 40, 64] {
+            let delay = policy.delay(retry);
+            assert!(delay >= Duration::from_millis(500) && delay <= Duration::from_secs(1), "{:?}", delay);
+        }
+    }
+
+    #[tokio::test]
+    async fn run_stops_after_max_retries() {
+        let attempts = Cell::new(0);
+        let result: Result<(), _> = policy(2)
+            .run("test", |attempt| {
+                assert_eq!(attempt, attempts.get());
+                attempts.set(attempts.get() + 1);
+                async { Err(network_error()) }
+            })
+            .await;
+        assert!(matches!(result, Err(crate::Error::Connection(_))));
+        assert_eq!(attempts.get(), 3);
+    }
+
+    #[tokio::test]
+    async fn run_returns_the_first_success() {
+        let attempts = Cell::new(0);
+        let result = policy(3)
+            .run("test", |attempt| {
+                attempts.set(attempts.get() + 1);
+                async move { if attempt < 2 { Err(network_error()) } else { Ok(attempt) } }
+            })
+            .await;
+        assert_eq!(result.unwrap(), 2);
+        assert_eq!(attempts.get(), 3);
+    }
+
+    #[tokio::test]
+    async fn run_does_not_retry_non_transient_errors() {
+        let attempts = Cell::new(0);
+        let result: Result<(), _> = policy(3)
+            .run("test", |_| {
+                attempts.set(attempts.get() + 1);
+                async { Err(crate::Error::Usage("bad argument".to_string())) }
+            })
+            .await;
+        assert!(matches!(result, Err(crate::Error::Usage(_))));
+        assert_eq!(attempts.get(), 1);
+    }
+}
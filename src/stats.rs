@@ -0,0 +1,83 @@
+use crate::model::KanaMapping;
+use futures::TryStreamExt;
+use mongodb::{
+    bson::{doc, Bson, Document},
+    Collection,
+};
+use std::collections::BTreeMap;
+use std::fmt;
+
+// 依原始字串分組統計, 不符合 KanaType / Category 的值也會列出
+#[derive(Debug, Clone, Default)]
+pub struct Stats {
+    pub total: u64,
+    pub by_group: BTreeMap<(String, String), u64>,
+}
+
+pub async fn collect(collection: &Collection<KanaMapping>) -> mongodb::error::Result<Stats> {
+    let pipeline = vec![
+        doc! { "$group": { "_id": { "kana_type": "$kana_type", "category": "$category" }, "count": { "$sum": 1 } } },
+        doc! { "$sort": { "_id.kana_type": 1, "_id.category": 1 } },
+    ];
+    let groups: Vec<Document> = collection.aggregate(pipeline, None).await?.try_collect().await?;
+
+    let mut stats = Stats::default();
+    for group in groups {
+        let id = group.get_document("_id").cloned().unwrap_or_default();
+        let field = |name: &str| match id.get(name) {
+            Some(Bson::String(value)) => value.clone(),
+            Some(value) => value.to_string(),
+            None => "(missing)".to_string(),
+        };
+        let count = match group.get("count") {
+            Some(Bson::Int32(n)) => *n as u64,
+            Some(Bson::Int64(n)) => *n as u64,
+            _ => 0,
+        };
+        stats.total += count;
+        stats.by_group.insert((field("kana_type"), field("category")), count);
+    }
+    Ok(stats)
+}
+
+impl fmt::Display for Stats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut by_kana_type: BTreeMap<&str, u64> = BTreeMap::new();
+        for ((kana_type, category), count) in &self.by_group {
+            *by_kana_type.entry(kana_type).or_default() += count;
+            writeln!(f, "{:<20} {:<20} {:>6}", kana_type, category, count)?;
+        }
+        for (kana_type, count) in by_kana_type {
+            writeln!(f, "{:<20} {:<20} {:>6}", kana_type, "(all)", count)?;
+        }
+        write!(f, "{:<20} {:<20} {:>6}", "(all)", "(all)", self.total)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn display_lists_groups_then_totals_per_kana_type() {
+        let by_group = [
+            (("hiragana".to_string(), "seion".to_string()), 46),
+            (("hiragana".to_string(), "youon".to_string()), 36),
+            (("katakana".to_string(), "seion".to_string()), 46),
+        ];
+        let stats = Stats { total: 128, by_group: by_group.into_iter().collect() };
+        let lines: Vec<String> = stats.to_string().lines().map(|line| line.split_whitespace().collect::<Vec<_>>().join(" ")).collect();
+        assert_eq!(
+            lines,
+            [
+                "hiragana seion 46",
+                "hiragana youon 36",
+                "katakana seion 46",
+                "hiragana (all) 82",
+                "katakana (all) 46",
+                "(all) (all) 128",
+            ]
+        );
+        assert_eq!(stats.to_string().lines().next(), Some(format!("{:<20} {:<20} {:>6}", "hiragana", "seion", 46)).as_deref());
+    }
+}
@@ -0,0 +1,154 @@
+use crate::model::{Category, KanaType, Role, Usage, ROMAJI_PATTERN};
+use futures::TryStreamExt;
+use mongodb::{
+    bson::{self, doc, Document},
+    error::ErrorKind,
+    options::{CreateCollectionOptions, ValidationAction, ValidationLevel},
+    Database,
+};
+use std::fmt;
+
+pub fn parse_validation_level(s: &str) -> Result<ValidationLevel, String> {
+    match s.to_ascii_lowercase().as_str() {
+        "off" => Ok(ValidationLevel::Off),
+        "moderate" => Ok(ValidationLevel::Moderate),
+        "strict" => Ok(ValidationLevel::Strict),
+        _ => Err(format!("unknown validation level `{}` (expected off, moderate or strict)", s)),
+    }
+}
+
+// 依 KanaMapping 的欄位產生 $jsonSchema, 未列出的額外欄位不受限制
+pub fn json_schema() -> Document {
+    let roles = |has_romaji: bool| -> Vec<&str> {
+        Role::ALL.iter().filter(|role| role.has_romaji() == has_romaji).map(|role| role.as_str()).collect()
+    };
+    let categories_without_role: Vec<&str> =
+        Category::ALL.iter().filter(|category| !category.requires_role()).map(|category| category.as_str()).collect();
+    doc! {
+        "$jsonSchema": {
+            "bsonType": "object",
+            "required": ["kana_type", "category", "kana", "romaji"],
+            // 與 dataset::validate 相同: 只有沒有讀音的 role 可以省略羅馬拼音, 小寫假名與符號必須有 role
+            "allOf": [
+                {
+                    "anyOf": [
+                        { "properties": { "romaji": { "pattern": ROMAJI_PATTERN }, "role": { "enum": roles(true) } } },
+                        { "required": ["role"], "properties": { "romaji": { "maxLength": 0 }, "role": { "enum": roles(false) } } },
+                    ],
+                },
+                {
+                    "anyOf": [
+                        { "properties": { "category": { "enum": categories_without_role } } },
+                        { "required": ["role"] },
+                    ],
+                },
+            ],
+            "properties": {
+                "kana_type": {
+                    "bsonType": "string",
+                    "enum": KanaType::ALL.iter().map(|kana_type| kana_type.as_str()).collect::<Vec<_>>(),
+                    "description": "type of the syllabary",
+                },
+                "category": {
+                    "bsonType": "string",
+                    "enum": Category::ALL.iter().map(|category| category.as_str()).collect::<Vec<_>>(),
+                    "description": "category of the kana in the syllabary table",
+                },
+                "kana": {
+                    "bsonType": "string",
+                    "minLength": 1,
+                    "description": "the kana itself",
+                },
+                "romaji": {
+                    "bsonType": "string",
+                    "description": "Hepburn romanization in lowercase ASCII letters, empty only for the sokuon, choonpu, voicing and iteration mark roles",
+                },
+                "usage": {
+                    "bsonType": "string",
+                    "enum": Usage::ALL.iter().map(|usage| usage.as_str()).collect::<Vec<_>>(),
+                    "description": "standard or rare loanword notation, extended_katakana only",
+                },
+                "role": {
+                    "bsonType": "string",
+                    "enum": Role::ALL.iter().map(|role| role.as_str()).collect::<Vec<_>>(),
+                    "description": "how a small kana or mark affects the romanization",
+                },
+                "fullwidth": {
+                    "bsonType": "string",
+                    "minLength": 1,
+                    "description": "full-width equivalent of a halfwidth_katakana kana",
+                },
+                "modern_kana": {
+                    "bsonType": "string",
+                    "minLength": 1,
+                    "description": "modern spelling of an archaic kana",
+                },
+                "beginner": {
+                    "bsonType": "bool",
+                    "description": "false to hide the kana from beginner drills",
+                },
+            },
+        }
+    }
+}
+
+// Unauthorized: 使用者缺少 collMod 或 createCollection 權限, 內建的 readWrite 角色沒有 collMod
+const UNAUTHORIZED: i32 = 13;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ValidatorStatus {
+    Created,
+    Updated,
+    UpToDate,
+}
+
+impl fmt::Display for ValidatorStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ValidatorStatus::Created => f.write_str("collection created with the validator"),
+            ValidatorStatus::Updated => f.write_str("validator updated"),
+            ValidatorStatus::UpToDate => f.write_str("validator already up to date"),
+        }
+    }
+}
+
+// 集合不存在時帶著 validator 建立, 定義或層級不同時以 collMod 更新, 已相同時不送出任何指令
+pub async fn ensure_validator(
+    db: &Database,
+    collection: &str,
+    level: ValidationLevel,
+) -> mongodb::error::Result<ValidatorStatus> {
+    let existing = db.list_collections(doc! { "name": collection }, None).await?.try_next().await?;
+    let Some(existing) = existing else {
+        let options = CreateCollectionOptions::builder()
+            .validator(json_schema())
+            .validation_level(level)
+            .validation_action(ValidationAction::Error)
+            .build();
+        db.create_collection(collection, options).await?;
+        return Ok(ValidatorStatus::Created);
+    };
+
+    let options = existing.options;
+    if options.validator == Some(json_schema())
+        && options.validation_level == Some(level.clone())
+        && options.validation_action == Some(ValidationAction::Error)
+    {
+        return Ok(ValidatorStatus::UpToDate);
+    }
+    db.run_command(
+        doc! {
+            "collMod": collection,
+            "validator": json_schema(),
+            "validationLevel": bson::to_bson(&level)?,
+            "validationAction": bson::to_bson(&ValidationAction::Error)?,
+        },
+        None,
+    )
+    .await?;
+    Ok(ValidatorStatus::Updated)
+}
+
+pub fn is_unauthorized(error: &mongodb::error::Error) -> bool {
+    matches!(*error.kind, ErrorKind::Command(ref command) if command.code == UNAUTHORIZED)
+}
@@ -0,0 +1,117 @@
+// 需要一個可寫入的 mongod, 預設連到 localhost:27017; transaction 的測試需要 replica set:
+//   KANA_TEST_URI=mongodb://localhost:27017 cargo test --test import -- --ignored
+use kana_data_importer::{
+    connection::{self, ConnectionConfig},
+    dataset, import, index,
+    import::{ImportMode, ImportOptions},
+    retry::RetryPolicy,
+    stats, Category, KanaMapping, KanaType,
+};
+use futures::TryStreamExt;
+use mongodb::{bson::doc, Collection};
+use std::env;
+
+const DATABASE: &str = "jp_syllabaries_import_test";
+
+async fn collection(name: &str) -> Collection<KanaMapping> {
+    let config = ConnectionConfig {
+        uri: Some(env::var("KANA_TEST_URI").unwrap_or_else(|_| "mongodb://localhost:27017".to_string())),
+        ..Default::default()
+    };
+    let client = connection::connect(&config, &RetryPolicy::default()).await.unwrap();
+    let collection = client.database(DATABASE).collection(name);
+    collection.drop(None).await.unwrap();
+    collection
+}
+
+// 舊版 importer 執行兩次後, 每個假名都有兩份文件
+#[tokio::test]
+#[ignore = "requires a mongod, see KANA_TEST_URI"]
+async fn imports_into_collection_with_duplicates() {
+    let collection = collection("duplicated").await;
+    let mappings = dataset::builtin().unwrap();
+    collection.insert_many(&mappings, None).await.unwrap();
+    collection.insert_many(&mappings, None).await.unwrap();
+
+    let retry = RetryPolicy::default();
+    assert_eq!(index::remove_duplicates(&collection, &retry, 100).await.unwrap(), mappings.len() as u64);
+    index::ensure_indexes(&collection).await.unwrap();
+
+    let summary = import::import(&collection, mappings.clone(), &ImportOptions::default()).await.unwrap();
+    assert_eq!((summary.inserted, summary.updated, summary.unchanged), (0, 0, mappings.len() as u64));
+    assert_eq!(collection.count_documents(None, None).await.unwrap(), mappings.len() as u64);
+    collection.drop(None).await.unwrap();
+}
+
+// upsert 只寫入資料集中的欄位, 其他應用程式加入的欄位保持不變
+#[tokio::test]
+#[ignore = "requires a mongod, see KANA_TEST_URI"]
+async fn upsert_keeps_foreign_fields() {
+    let collection = collection("foreign_fields").await;
+    let mappings = dataset::builtin().unwrap();
+    index::ensure_indexes(&collection).await.unwrap();
+    import::import(&collection, mappings.clone(), &ImportOptions::default()).await.unwrap();
+
+    let key = doc! { "kana_type": "hiragana", "kana": "あ" };
+    collection.update_one(key.clone(), doc! { "$set": { "audio": "a.mp3", "romaji": "x" } }, None).await.unwrap();
+    let summary = import::import(&collection, mappings, &ImportOptions::default()).await.unwrap();
+    assert_eq!(summary.updated, 1);
+
+    let document = collection.clone_with_type::<mongodb::bson::Document>().find_one(key, None).await.unwrap().unwrap();
+    assert_eq!(document.get_str("romaji"), Ok("a"));
+    assert_eq!(document.get_str("audio"), Ok("a.mp3"));
+    collection.drop(None).await.unwrap();
+}
+
+// stats 依 (kana_type, category) 分組, 總數與匯入的筆數相同
+#[tokio::test]
+#[ignore = "requires a mongod, see KANA_TEST_URI"]
+async fn stats_count_each_group() {
+    let collection = collection("stats").await;
+    let mappings = dataset::builtin().unwrap();
+    collection.insert_many(&mappings, None).await.unwrap();
+
+    let stats = stats::collect(&collection).await.unwrap();
+    assert_eq!(stats.total, mappings.len() as u64);
+    let seion = mappings.iter().filter(|m| m.kana_type == KanaType::Hiragana && m.category == Category::Seion).count();
+    assert_eq!(stats.by_group[&("hiragana".to_string(), "seion".to_string())], seion as u64);
+    collection.drop(None).await.unwrap();
+}
+
+// 最後一批中的文件與唯一索引衝突時放棄 transaction, 先前批次的寫入也不會留下
+#[tokio::test]
+#[ignore = "requires a replica set, see KANA_TEST_URI"]
+async fn aborted_transaction_leaves_collection_unchanged() {
+    let collection = collection("aborted_transaction").await;
+    let mappings = dataset::builtin().unwrap();
+    index::ensure_indexes(&collection).await.unwrap();
+    let last = mappings.last().unwrap();
+    collection.insert_one(last, None).await.unwrap();
+
+    let options = ImportOptions { mode: ImportMode::Insert, batch_size: 100, ..Default::default() };
+    let summary = import::import_in_transaction(&collection, &mappings, &options).await.unwrap();
+    assert_eq!(summary.inserted, 0);
+    assert_eq!(summary.failed.len(), 1);
+    assert_eq!(summary.failed[0].kana, last.kana);
+
+    let documents: Vec<KanaMapping> = collection.find(None, None).await.unwrap().try_collect().await.unwrap();
+    assert_eq!(documents, std::slice::from_ref(last));
+    collection.drop(None).await.unwrap();
+}
+
+// prune 在同一個 transaction 中刪除資料集以外的文件
+#[tokio::test]
+#[ignore = "requires a replica set, see KANA_TEST_URI"]
+async fn transaction_upserts_and_prunes() {
+    let collection = collection("committed_transaction").await;
+    let mappings = dataset::builtin().unwrap();
+    index::ensure_indexes(&collection).await.unwrap();
+    let stale = KanaMapping::new(KanaType::Hiragana, Category::Archaic, "𛀁", "e");
+    collection.insert_one(&stale, None).await.unwrap();
+
+    let options = ImportOptions { prune: true, ..Default::default() };
+    let summary = import::import_in_transaction(&collection, &mappings, &options).await.unwrap();
+    assert_eq!((summary.inserted, summary.deleted), (mappings.len() as u64, 1));
+    assert_eq!(collection.count_documents(None, None).await.unwrap(), mappings.len() as u64);
+    collection.drop(None).await.unwrap();
+}
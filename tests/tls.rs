@@ -0,0 +1,41 @@
+// 需要先以 scripts/tls-mongod.sh 啟動使用自簽憑證的 mongod:
+//   scripts/tls-mongod.sh && cargo test --test tls -- --ignored
+use kana_data_importer::{
+    connection::{self, ConnectionConfig},
+    retry::RetryPolicy,
+    Error,
+};
+use mongodb::{bson::doc, options::AuthMechanism};
+use std::env;
+use std::path::PathBuf;
+
+const DATABASE: &str = "jp_syllabaries_tls_test";
+
+fn config() -> ConnectionConfig {
+    let dir = PathBuf::from(env::var("KANA_TLS_TEST_DIR").unwrap_or_else(|_| "target/tls".to_string()));
+    let port = env::var("KANA_TLS_TEST_PORT").unwrap_or_else(|_| "27443".to_string());
+    ConnectionConfig {
+        uri: Some(format!("mongodb://localhost:{}/{}?tls=true", port, DATABASE)),
+        auth_mechanism: Some(AuthMechanism::MongoDbX509),
+        tls_ca_file: Some(dir.join("ca.pem")),
+        tls_certificate_key_file: Some(dir.join("client.pem")),
+        ..Default::default()
+    }
+}
+
+#[tokio::test]
+#[ignore = "requires the mongod started by scripts/tls-mongod.sh"]
+async fn connects_with_client_certificate() {
+    let client = connection::connect(&config(), &RetryPolicy::default()).await.unwrap();
+    let collection = client.database(DATABASE).collection("tls_check");
+    collection.insert_one(doc! {"kana": "あ", "romaji": "a"}, None).await.unwrap();
+    assert_eq!(collection.count_documents(None, None).await.unwrap(), 1);
+    collection.drop(None).await.unwrap();
+}
+
+#[tokio::test]
+#[ignore = "requires the mongod started by scripts/tls-mongod.sh"]
+async fn rejects_server_without_trusted_ca() {
+    let config = ConnectionConfig { tls_ca_file: None, ..config() };
+    assert!(matches!(connection::connect(&config, &RetryPolicy { max_retries: 0, ..Default::default() }).await, Err(Error::Connection(_))));
+}